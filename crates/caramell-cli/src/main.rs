@@ -1,9 +1,738 @@
+use caramell::chords::ChordStyle;
+use caramell::markdown;
+use caramell::pdf::{self, Fonts, PageOptions, PageSize};
+use caramell::pitch::Notation;
+use caramell::printing::{self, ChordRows, ChorusRecall};
+use caramell::transpose::{self, Transposition};
+use caramell::{Diagnostic, Song};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
 
-fn main() {
-    let song =
-        // fs::read_to_string("../../examples/input/rickroll.cho").expect("Unable to read file");
-        fs::read_to_string("../../examples/input/allstar.cho").expect("Unable to read file");
+/// Beats per bar of bar notation when a song has no `{time}`
+const DEFAULT_BEATS: u8 = 4;
 
-    caramell::print(song);
+/// Name shown for a song read from stdin
+const STDIN: &str = "<stdin>";
+
+/// A lyric and chord sheet formatter for ChordPro songs
+///
+/// Songs are read from the given files, or from stdin when there are none or a file is `-`.
+/// Exits with 1 if a song has errors or cannot be transposed, and 2 if the arguments are invalid,
+/// a file cannot be read or written, or the songs cannot be laid out on the pages
+#[derive(Debug, Parser)]
+#[command(name = "caramell", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Print songs as plain text, with the chords above the lyrics
+    Render {
+        #[command(flatten)]
+        io: Io,
+        #[command(flatten)]
+        layout: Layout,
+    },
+    /// Transpose every chord of songs, along with their key
+    Transpose {
+        /// Half steps to move by (e.g. `-2` or `3`), or the key to move to (e.g. `Bb`, or `F#m` for a
+        /// song in a minor key); a key without a mode keeps the mode of the song
+        #[arg(allow_negative_numbers = true)]
+        by: Transposition,
+        /// Format to write the transposed songs in
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        to: Format,
+        #[command(flatten)]
+        io: Io,
+        #[command(flatten)]
+        layout: Layout,
+        #[command(flatten)]
+        documents: Documents,
+    },
+    /// Convert songs to another format
+    Convert {
+        /// Format to write the songs in
+        #[arg(long, value_enum)]
+        to: Format,
+        #[command(flatten)]
+        io: Io,
+        #[command(flatten)]
+        layout: Layout,
+        #[command(flatten)]
+        documents: Documents,
+    },
+    /// Rewrite songs as ChordPro with every chord in the same style
+    ///
+    /// Everything but the chords, including directives, comments and line endings, is kept as written
+    Normalize {
+        /// Rewrite the files instead of printing them
+        #[arg(short, long, conflicts_with = "output")]
+        in_place: bool,
+        #[command(flatten)]
+        io: Io,
+        #[command(flatten)]
+        chord_names: ChordNames,
+    },
+    /// Report every problem in songs, without printing them
+    Lint {
+        #[command(flatten)]
+        io: Io,
+    },
+    /// Print the metadata, sections and chords of songs
+    Info {
+        #[command(flatten)]
+        io: Io,
+    },
+}
+
+#[derive(Debug, Args)]
+struct Io {
+    /// ChordPro files to read, or `-` for stdin
+    files: Vec<PathBuf>,
+    /// File to write to instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct Layout {
+    /// Pad lyrics under wide chords so chord names never touch
+    #[arg(short, long)]
+    stretch: bool,
+    /// Print lines of chords without lyrics as bars, e.g. `| C . . . | G . . . |`
+    #[arg(short, long)]
+    bars: bool,
+    /// Print repeated choruses in full instead of just their heading
+    #[arg(long)]
+    full_choruses: bool,
+    #[command(flatten)]
+    chord_names: ChordNames,
+}
+
+impl Layout {
+    fn options(&self) -> printing::Options {
+        printing::Options {
+            chorus_recall: if self.full_choruses {
+                ChorusRecall::Full
+            } else {
+                ChorusRecall::Reference
+            },
+            chord_rows: if self.bars {
+                ChordRows::Bars {
+                    beats: DEFAULT_BEATS,
+                }
+            } else {
+                ChordRows::Compact
+            },
+            stretch_lyrics: self.stretch,
+            chord_style: self.chord_names.style(),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+struct ChordNames {
+    /// How to write the qualities of chords
+    #[arg(long, value_enum, default_value_t = Style::Standard)]
+    chord_style: Style,
+    /// Write accidentals in chords as ♯ and ♭ instead of # and b
+    #[arg(long)]
+    typographic: bool,
+}
+
+impl ChordNames {
+    fn style(&self) -> ChordStyle {
+        let style = match self.chord_style {
+            Style::Standard => ChordStyle::STANDARD,
+            Style::Spelled => ChordStyle::SPELLED,
+            Style::Short => ChordStyle::SHORT,
+            Style::Jazz => ChordStyle::JAZZ,
+        };
+        ChordStyle {
+            notation: if self.typographic {
+                Notation::Typographic
+            } else {
+                Notation::Ascii
+            },
+            ..style
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, ValueEnum)]
+enum Style {
+    /// CMaj7, Cm, Cdim, Caug and Cm7b5
+    Standard,
+    /// Cmaj7, Cmin, Cdim, Caug and Cmin7b5
+    Spelled,
+    /// CM7, Cm, C°, C+ and Cø7
+    Short,
+    /// CΔ7, C-, C°, C+ and Cø7
+    Jazz,
+}
+
+/// Options for particular output formats
+#[derive(Debug, Args)]
+struct Documents {
+    #[command(flatten)]
+    pages: Pages,
+    #[command(flatten)]
+    html: Html,
+    #[command(flatten)]
+    markdown: Markdown,
+}
+
+#[derive(Debug, Args)]
+#[command(next_help_heading = "PDF")]
+struct Pages {
+    /// Size of the pages: `a4`, `letter`, or WIDTHxHEIGHT in millimetres
+    #[arg(long, default_value_t = PageSize::A4)]
+    page_size: PageSize,
+    /// Space around the text on every side, in millimetres
+    #[arg(long, default_value_t = 20.0)]
+    margin: f32,
+    /// Number of columns on each page
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..))]
+    columns: u8,
+    /// Size of the lyrics, in points
+    #[arg(long, default_value_t = 11.0)]
+    font_size: f32,
+    /// TrueType or OpenType font for lyrics [default: the bundled DejaVu Sans]
+    #[arg(long)]
+    font: Option<PathBuf>,
+    /// Font for chords, headings and titles [default: the bundled DejaVu Sans Bold, or --font if
+    /// given]
+    #[arg(long)]
+    bold_font: Option<PathBuf>,
+}
+
+impl Pages {
+    fn options(&self) -> PageOptions {
+        PageOptions {
+            page_size: self.page_size,
+            margin: self.margin,
+            columns: self.columns,
+            font_size: self.font_size,
+        }
+    }
+
+    /// Reads the fonts to embed, falling back to the bundled DejaVu Sans when none are given
+    fn fonts(&self) -> Result<Fonts, Failure> {
+        let read = |path: &PathBuf| {
+            fs::read(path)
+                .map_err(|err| Failure::Io(format!("cannot read font '{}': {err}", path.display())))
+        };
+        let regular = match &self.font {
+            Some(font) => read(font)?,
+            None => pdf::BUNDLED_FONT.to_vec(),
+        };
+        let bold = match (&self.bold_font, &self.font) {
+            (Some(bold), _) => read(bold)?,
+            (None, Some(_)) => regular.clone(),
+            (None, None) => pdf::BUNDLED_BOLD_FONT.to_vec(),
+        };
+        Fonts::new(regular, bold).map_err(Failure::Io)
+    }
+}
+
+#[derive(Debug, Args)]
+#[command(next_help_heading = "HTML")]
+struct Html {
+    /// Write a complete page with an embedded stylesheet, instead of just the songs
+    #[arg(long)]
+    standalone: bool,
+}
+
+#[derive(Debug, Args)]
+#[command(next_help_heading = "Markdown")]
+struct Markdown {
+    /// Write chords in bold brackets within the lyrics, e.g. `**[G]**Somebody`,
+    /// instead of above them in code blocks
+    #[arg(long)]
+    inline_chords: bool,
+}
+
+impl Markdown {
+    fn chords(&self) -> markdown::Chords {
+        if self.inline_chords {
+            markdown::Chords::Inline
+        } else {
+            markdown::Chords::Above
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, ValueEnum)]
+enum Format {
+    /// Plain text, with the chords above the lyrics
+    Text,
+    /// ChordPro, with the chords in brackets within the lyrics
+    Chordpro,
+    /// A Typst document, to typeset with `typst compile`
+    Typst,
+    /// A PDF document, laid out with embedded fonts
+    Pdf,
+    /// HTML, with each chord positioned above its lyrics
+    Html,
+    /// GitHub-flavored Markdown, with a heading per section
+    Markdown,
+}
+
+/// Why a command failed
+#[derive(Debug)]
+enum Failure {
+    /// Some songs have errors, which were already reported
+    InvalidSongs,
+    /// A song could not be transposed, with the problem rendered against its source
+    Transpose(String),
+    /// The songs could not be laid out, e.g. with margins that leave no room on the page
+    Render(String),
+    /// A file could not be read or written, or the arguments do not fit together
+    Io(String),
+}
+
+impl From<Failure> for ExitCode {
+    fn from(failure: Failure) -> Self {
+        match failure {
+            Failure::InvalidSongs => ExitCode::from(1),
+            Failure::Transpose(report) => {
+                eprint!("{report}");
+                ExitCode::from(1)
+            }
+            Failure::Render(err) => {
+                eprintln!("error: cannot lay out the songs: {err}");
+                ExitCode::from(2)
+            }
+            Failure::Io(err) => {
+                eprintln!("error: {err}");
+                ExitCode::from(2)
+            }
+        }
+    }
+}
+
+/// The source of a song, along with where it was read from
+struct Input {
+    path: String,
+    source: String,
+}
+
+impl Input {
+    /// Parses the song, reporting every problem in it on stderr
+    fn parse(&self) -> Result<Song, Failure> {
+        let (song, diagnostics) = Song::parse_recovering(self.source.clone());
+        if diagnostics.is_empty() {
+            return Ok(song);
+        }
+        self.report(&diagnostics);
+        Err(Failure::InvalidSongs)
+    }
+
+    /// Renders why `song` could not be transposed, pointing at where its key was set
+    fn transpose_error(&self, song: &Song, message: String) -> String {
+        let span = transpose::key_span(&song.lines).unwrap_or_default();
+        Diagnostic::error(&self.source, span, message).render(&self.source, &self.path)
+    }
+
+    fn report(&self, diagnostics: &[Diagnostic]) {
+        for diagnostic in diagnostics {
+            eprintln!("{}", diagnostic.render(&self.source, &self.path));
+        }
+    }
+}
+
+fn read_inputs(files: &[PathBuf]) -> Result<Vec<Input>, Failure> {
+    let stdin = [PathBuf::from("-")];
+    let files = if files.is_empty() { &stdin[..] } else { files };
+    if files.iter().filter(|file| file.as_os_str() == "-").count() > 1 {
+        return Err(Failure::Io(
+            "stdin can only be read once; give `-` once".to_string(),
+        ));
+    }
+    files
+        .iter()
+        .map(|file| {
+            if file.as_os_str() == "-" {
+                let mut source = String::new();
+                io::stdin()
+                    .read_to_string(&mut source)
+                    .map_err(|err| Failure::Io(format!("cannot read stdin: {err}")))?;
+                Ok(Input {
+                    path: STDIN.to_string(),
+                    source,
+                })
+            } else {
+                let source = fs::read_to_string(file).map_err(|err| {
+                    Failure::Io(format!("cannot read '{}': {err}", file.display()))
+                })?;
+                Ok(Input {
+                    path: file.display().to_string(),
+                    source,
+                })
+            }
+        })
+        .collect()
+}
+
+fn write_output(output: &Option<PathBuf>, contents: impl AsRef<[u8]>) -> Result<(), Failure> {
+    match output {
+        Some(path) => fs::write(path, contents)
+            .map_err(|err| Failure::Io(format!("cannot write '{}': {err}", path.display()))),
+        None => io::stdout()
+            .write_all(contents.as_ref())
+            .map_err(|err| Failure::Io(format!("cannot write to stdout: {err}"))),
+    }
+}
+
+fn format_song(song: Song, format: Format, options: &printing::Options) -> String {
+    match format {
+        Format::Text => printing::fmt_song(song, options),
+        Format::Chordpro => {
+            let mut chordpro = printing::fmt_chordpro(&song.lines, &options.chord_style);
+            if !chordpro.ends_with('\n') {
+                chordpro.push('\n');
+            }
+            chordpro
+        }
+        Format::Typst => caramell::typst::fmt_song(song, options),
+        Format::Html => caramell::html::fmt_song(song, options),
+        Format::Markdown | Format::Pdf => unreachable!("{format:?} is formatted by format_songs"),
+    }
+}
+
+/// Formats every song, or reports the problems in all of them if any has errors
+fn format_songs(
+    io: &Io,
+    format: Format,
+    options: &printing::Options,
+    documents: Option<&Documents>,
+    transposition: Option<&Transposition>,
+) -> Result<(), Failure> {
+    if format == Format::Pdf && io.output.is_none() && io::stdout().is_terminal() {
+        return Err(Failure::Io(
+            "refusing to write a PDF to the terminal; choose a file with --output".to_string(),
+        ));
+    }
+    let inputs = read_inputs(&io.files)?;
+    let mut songs = Vec::new();
+    let mut invalid = false;
+    for input in &inputs {
+        match input.parse() {
+            Ok(song) => songs.push(song),
+            Err(_) => invalid = true,
+        }
+    }
+    if invalid {
+        return Err(Failure::InvalidSongs);
+    }
+    let songs = songs
+        .into_iter()
+        .zip(&inputs)
+        .map(|(song, input)| match transposition {
+            Some(transposition) => song
+                .transpose(transposition)
+                .map_err(|message| Failure::Transpose(input.transpose_error(&song, message))),
+            None => Ok(song),
+        })
+        .collect::<Result<Vec<Song>, Failure>>()?;
+    // Typst, PDF and standalone HTML get a single document with every song,
+    // instead of one document per song
+    let formatted = match format {
+        Format::Typst => caramell::typst::fmt_songs(songs, options).into_bytes(),
+        Format::Html if documents.is_some_and(|documents| documents.html.standalone) => {
+            caramell::html::fmt_page(songs, options).into_bytes()
+        }
+        Format::Markdown => {
+            let chords =
+                documents.map_or(Default::default(), |documents| documents.markdown.chords());
+            markdown::fmt_songs(songs, options, chords).into_bytes()
+        }
+        Format::Pdf => {
+            let pages = &documents.expect("page options for a PDF").pages;
+            pdf::fmt_songs(songs, options, &pages.options(), &pages.fonts()?)
+                .map_err(Failure::Render)?
+        }
+        _ => songs
+            .into_iter()
+            .map(|song| format_song(song, format, options))
+            .collect::<Vec<String>>()
+            .join("\n")
+            .into_bytes(),
+    };
+    write_output(&io.output, &formatted)
+}
+
+/// Rewrites the chords of every song in one chord style, leaving the rest of the songs
+/// as written, and every file untouched if any of the songs has errors
+fn normalize(io: &Io, in_place: bool, style: ChordStyle) -> Result<(), Failure> {
+    if in_place && (io.files.is_empty() || io.files.iter().any(|file| file.as_os_str() == "-")) {
+        return Err(Failure::Io(
+            "cannot rewrite stdin in place; give the files to rewrite".to_string(),
+        ));
+    }
+    let inputs = read_inputs(&io.files)?;
+    let mut normalized = Vec::new();
+    let mut invalid = false;
+    for input in &inputs {
+        match input.parse() {
+            Ok(_) => normalized.push(printing::fmt_chords_in_place(&input.source, &style)),
+            Err(_) => invalid = true,
+        }
+    }
+    if invalid {
+        return Err(Failure::InvalidSongs);
+    }
+    if !in_place {
+        return write_output(&io.output, normalized.join("\n"));
+    }
+    for ((file, input), chordpro) in io.files.iter().zip(&inputs).zip(normalized) {
+        if chordpro != input.source {
+            write_output(&Some(file.clone()), chordpro)?;
+        }
+    }
+    Ok(())
+}
+
+fn lint(io: &Io) -> Result<(), Failure> {
+    let mut report = String::new();
+    let mut problems = 0;
+    for input in read_inputs(&io.files)? {
+        let (_, diagnostics) = Song::parse_recovering(input.source.clone());
+        for diagnostic in &diagnostics {
+            report.push_str(&diagnostic.render(&input.source, &input.path));
+            report.push('\n');
+        }
+        problems += diagnostics.len();
+    }
+    write_output(&io.output, &report)?;
+    if problems > 0 {
+        eprintln!(
+            "found {problems} problem{}",
+            if problems == 1 { "" } else { "s" }
+        );
+        return Err(Failure::InvalidSongs);
+    }
+    Ok(())
+}
+
+/// Metadata, sections and chords of a song, one per line
+fn fmt_info(song: &Song) -> String {
+    let mut info = printing::fmt_header(&song.metadata).trim_end().to_string();
+    let mut push_line = |line: String| {
+        if !info.is_empty() {
+            info.push('\n');
+        }
+        info.push_str(&line);
+    };
+    for (name, value) in &song.metadata.other {
+        push_line(format!("{name}: {value}"));
+    }
+    if song.metadata.key.is_none() {
+        if let Some(key) = transpose::guess_key(&song.lines) {
+            push_line(format!("Key (guessed): {key}"));
+        }
+    }
+    let sections: Vec<String> = song
+        .sections
+        .iter()
+        .map(|section| section.heading())
+        .filter(|heading| !heading.is_empty())
+        .collect();
+    if !sections.is_empty() {
+        push_line(format!("Sections: {}", sections.join(", ")));
+    }
+    let mut chords: Vec<String> = Vec::new();
+    for chord in song.lines.phrases().filter_map(|p| p.chord.as_ref()) {
+        let chord = chord.to_string();
+        if !chords.contains(&chord) {
+            chords.push(chord);
+        }
+    }
+    if !chords.is_empty() {
+        push_line(format!("Chords: {}", chords.join(" ")));
+    }
+    info.push('\n');
+    info
+}
+
+fn info(io: &Io) -> Result<(), Failure> {
+    let inputs = read_inputs(&io.files)?;
+    let mut infos = Vec::new();
+    let mut invalid = false;
+    for input in &inputs {
+        match input.parse() {
+            Ok(song) if inputs.len() > 1 => {
+                infos.push(format!("==> {} <==\n{}", input.path, fmt_info(&song)))
+            }
+            Ok(song) => infos.push(fmt_info(&song)),
+            Err(_) => invalid = true,
+        }
+    }
+    write_output(&io.output, infos.join("\n"))?;
+    if invalid {
+        return Err(Failure::InvalidSongs);
+    }
+    Ok(())
+}
+
+fn run(cli: Cli) -> Result<(), Failure> {
+    match cli.command {
+        Command::Render { io, layout } => {
+            format_songs(&io, Format::Text, &layout.options(), None, None)
+        }
+        Command::Transpose {
+            by,
+            to,
+            io,
+            layout,
+            documents,
+        } => format_songs(&io, to, &layout.options(), Some(&documents), Some(&by)),
+        Command::Convert {
+            to,
+            io,
+            layout,
+            documents,
+        } => format_songs(&io, to, &layout.options(), Some(&documents), None),
+        Command::Normalize {
+            in_place,
+            io,
+            chord_names,
+        } => normalize(&io, in_place, chord_names.style()),
+        Command::Lint { io } => lint(&io),
+        Command::Info { io } => info(&io),
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(failure) => failure.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn verify_cli() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn parse_transpose_arguments() {
+        let cli = Cli::parse_from(["caramell", "transpose", "-2", "song.cho", "-o", "out.txt"]);
+        let Command::Transpose { by, to, io, .. } = cli.command else {
+            panic!("expected the transpose command");
+        };
+        assert_eq!(by, Transposition::HalfSteps(-2));
+        assert_eq!(to, Format::Text);
+        assert_eq!(io.files, vec![PathBuf::from("song.cho")]);
+        assert_eq!(io.output, Some(PathBuf::from("out.txt")));
+    }
+
+    #[test]
+    fn parse_pdf_arguments() {
+        let cli = Cli::parse_from([
+            "caramell",
+            "convert",
+            "--to",
+            "pdf",
+            "--page-size",
+            "letter",
+            "--columns",
+            "2",
+            "song.cho",
+        ]);
+        let Command::Convert { to, documents, .. } = cli.command else {
+            panic!("expected the convert command");
+        };
+        assert_eq!(to, Format::Pdf);
+        assert_eq!(
+            documents.pages.options(),
+            PageOptions {
+                page_size: PageSize::Letter,
+                columns: 2,
+                ..Default::default()
+            }
+        );
+        assert!(
+            Cli::try_parse_from(["caramell", "convert", "--to", "pdf", "--columns", "0"]).is_err()
+        );
+    }
+
+    #[test]
+    fn parse_chord_style_arguments() {
+        let cli = Cli::parse_from([
+            "caramell",
+            "normalize",
+            "--chord-style",
+            "jazz",
+            "--typographic",
+            "-i",
+            "song.cho",
+        ]);
+        let Command::Normalize {
+            in_place,
+            chord_names,
+            ..
+        } = cli.command
+        else {
+            panic!("expected the normalize command");
+        };
+        assert!(in_place);
+        assert_eq!(
+            chord_names.style(),
+            ChordStyle {
+                notation: Notation::Typographic,
+                ..ChordStyle::JAZZ
+            }
+        );
+        assert!(Cli::try_parse_from(["caramell", "normalize", "-i", "-o", "out.cho"]).is_err());
+    }
+
+    #[test]
+    fn normalize_in_place_keeps_line_endings_and_comments() {
+        let path =
+            std::env::temp_dir().join(format!("caramell-normalize-{}.cho", std::process::id()));
+        fs::write(
+            &path,
+            "# Intro in Cmaj7\r\n[Cmaj7]Hi [Amin]there\r\n# Outro\r\n",
+        )
+        .unwrap();
+        let io = Io {
+            files: vec![path.clone()],
+            output: None,
+        };
+        normalize(&io, true, ChordStyle::STANDARD).unwrap();
+        let normalized = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            normalized,
+            "# Intro in Cmaj7\r\n[CMaj7]Hi [Am]there\r\n# Outro\r\n"
+        );
+    }
+
+    #[test]
+    fn read_stdin_only_once() {
+        let files = [PathBuf::from("-"), PathBuf::from("-")];
+        assert!(matches!(read_inputs(&files), Err(Failure::Io(_))));
+    }
+
+    #[test]
+    fn test_fmt_info() {
+        let song =
+            Song::parse("{title: Hi}\n{sov: Verse 1}\n[G]Hey [D]you [G]there\n{eov}".to_string())
+                .unwrap();
+        assert_eq!(
+            fmt_info(&song),
+            "Hi\nKey (guessed): G\nSections: Verse 1\nChords: G D\n"
+        );
+    }
 }
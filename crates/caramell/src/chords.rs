@@ -1,44 +1,631 @@
-use crate::pitch::{Note, PitchClass};
+use crate::pitch::{Accidental, Interval, IntervalQuality, Notation, Note};
+use std::fmt;
+use std::ops::Range;
 
-#[derive(Debug, PartialEq)]
+/// Symbols of a major 7th, e.g. `Maj` in `CMaj7` or `M` in `CmM7`
+const MAJOR_SEVENTH_SYMBOLS: &[&str] = &["Maj", "maj", "M", "Δ"];
+
+/// Symbols of a minor triad, in the order they must be matched, so `min` is not read as `m`
+const MINOR_SYMBOLS: &[&str] = &["min", "m", "-"];
+
+/// Symbols of the other qualities
+const QUALITY_SYMBOLS: &[(&str, ChordQuality)] = &[
+    ("ø", ChordQuality::HalfDiminished),
+    ("°", ChordQuality::Diminished),
+    ("+", ChordQuality::Augmented),
+    ("sus", ChordQuality::Sus),
+    ("dim", ChordQuality::Diminished),
+    ("aug", ChordQuality::Augmented),
+];
+
+/// Accidentals in the order they must be matched, so that e.g. `bb` is not read as `b`
+const ACCIDENTAL_SYMBOLS: &[&str] = &["##", "bb", "x", "𝄪", "𝄫", "#", "♯", "b", "♭", "♮"];
+
+/// Degrees that can be raised or lowered, e.g. the `5` in `C7b5`
+const ALTERED_DEGREES: &[u8] = &[2, 4, 5, 6, 9, 11, 13];
+
+/// Degrees that can be added to a chord, e.g. the `9` in `Cadd9`
+const ADDED_DEGREES: &[u8] = &[2, 4, 6, 9, 11, 13];
+
+/// Degrees that can replace the 3rd of a chord, e.g. the `4` in `C7sus4`
+const SUSPENDED_DEGREES: &[u8] = &[2, 4];
+
+#[derive(Debug, PartialEq, Copy, Clone)]
 pub enum ChordQuality {
     Diminished,
+    /// Diminished triad with a minor 7th, even without an extension, e.g. `Cø` or `Cm7b5`
+    HalfDiminished,
     Minor,
+    /// No quality symbol; a 7th (or higher) extension makes it a dominant chord, e.g. `C7`
     Major,
     Augmented,
+    /// Suspended chord; the extension is the suspended degree, e.g. `Csus2`
     Sus,
+    /// Major triad with a major 7th when extended, e.g. `CMaj7`
+    MajorSeventh,
+    /// Minor triad with a major 7th when extended, e.g. `CmMaj7`
+    MinorMajor,
 }
 
-#[derive(Debug, PartialEq)]
+impl ChordQuality {
+    /// Symbol of the quality in the given style, e.g. `Maj`, `M` or `Δ` for a major 7th
+    pub fn symbol(&self, style: &ChordStyle) -> String {
+        match self {
+            Self::Diminished => style.diminished.to_string(),
+            // The flat 5th of e.g. `m7b5` follows the notation of the other accidentals
+            Self::HalfDiminished => style
+                .half_diminished
+                .replace('b', Accidental::Flat.symbol(style.notation)),
+            Self::Minor => style.minor.to_string(),
+            Self::Major => String::new(),
+            Self::Augmented => style.augmented.to_string(),
+            Self::Sus => "sus".to_string(),
+            Self::MajorSeventh => style.major_seventh.to_string(),
+            Self::MinorMajor => format!("{}{}", style.minor, style.major_seventh),
+        }
+    }
+}
+
+/// How the qualities and accidentals of chords are written, e.g. `CMaj7`, `CM7` or `CΔ7`
+///
+/// Any of the presets can be parsed back into the same chords
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ChordStyle {
+    /// e.g. `Maj`, `maj`, `M` or `Δ`
+    pub major_seventh: &'static str,
+    /// e.g. `m`, `min` or `-`
+    pub minor: &'static str,
+    /// e.g. `dim` or `°`
+    pub diminished: &'static str,
+    /// e.g. `aug` or `+`
+    pub augmented: &'static str,
+    /// e.g. `m7b5` or `ø`; a symbol with a 7 in it stands for the 7th,
+    /// which a higher extension replaces, e.g. `Cm9b5`
+    pub half_diminished: &'static str,
+    pub notation: Notation,
+}
+
+impl ChordStyle {
+    /// `CMaj7`, `Cm`, `Cdim`, `Caug` and `Cm7b5`
+    pub const STANDARD: Self = Self {
+        major_seventh: "Maj",
+        minor: "m",
+        diminished: "dim",
+        augmented: "aug",
+        half_diminished: "m7b5",
+        notation: Notation::Ascii,
+    };
+
+    /// `Cmaj7`, `Cmin`, `Cdim`, `Caug` and `Cmin7b5`, with every quality spelled out
+    pub const SPELLED: Self = Self {
+        major_seventh: "maj",
+        minor: "min",
+        half_diminished: "min7b5",
+        ..Self::STANDARD
+    };
+
+    /// `CM7`, `Cm`, `C°`, `C+` and `Cø7`
+    pub const SHORT: Self = Self {
+        major_seventh: "M",
+        diminished: "°",
+        augmented: "+",
+        half_diminished: "ø",
+        ..Self::STANDARD
+    };
+
+    /// `CΔ7`, `C-`, `C°`, `C+` and `Cø7`, as on jazz lead sheets
+    pub const JAZZ: Self = Self {
+        major_seventh: "Δ",
+        minor: "-",
+        ..Self::SHORT
+    };
+}
+
+impl Default for ChordStyle {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+/// A chord tone written after the extension, e.g. `b9` in `C7b9`, or in braces,
+/// e.g. `6` in `C7{6,9}`
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Alteration {
+    /// A raised or lowered tone, e.g. `#11`, in place of the unaltered one
+    Altered(Accidental, u8),
+    /// A tone added without the ones between it and the triad, e.g. `add9`,
+    /// or the `9` in `C6/9`
+    Added(u8),
+    /// A 2nd or 4th in place of the 3rd, e.g. `sus4` in `C7sus4`
+    Suspended(u8),
+}
+
+impl Alteration {
+    /// Parses the alteration at the start of `s`, returning it with the rest of `s`
+    ///
+    /// In braces a bare number is an added tone, e.g. the `6` in `C7{6,9}`.
+    /// On failure, returns how many bytes of `s` are wrong along with the error
+    fn split(s: &str, braced: bool) -> Result<(Self, &str), (usize, String)> {
+        let symbol = ["add", "sus", "b", "♭", "#", "♯"]
+            .into_iter()
+            .find(|symbol| s.starts_with(symbol))
+            .or_else(|| (braced && s.starts_with(|c: char| c.is_ascii_digit())).then_some(""))
+            .ok_or_else(|| {
+                let unknown = s.find(['{', '/']).unwrap_or(s.len());
+                (unknown, format!("unknown alteration '{}'", &s[..unknown]))
+            })?;
+        let (digits, rest) = split_number(&s[symbol.len()..]);
+        let written = &s[..s.len() - rest.len()];
+        let degree = match digits.parse::<u8>() {
+            Ok(degree) => degree,
+            Err(_) if symbol == "sus" => return Ok((Self::Suspended(4), rest)),
+            Err(_) => return Err((written.len(), format!("missing degree after '{written}'"))),
+        };
+        let (alteration, degrees, verb) = match symbol {
+            "sus" => (Self::Suspended(degree), SUSPENDED_DEGREES, "suspend to"),
+            "b" | "♭" => (
+                Self::Altered(Accidental::Flat, degree),
+                ALTERED_DEGREES,
+                "lower",
+            ),
+            "#" | "♯" => (
+                Self::Altered(Accidental::Sharp, degree),
+                ALTERED_DEGREES,
+                "raise",
+            ),
+            _ => (Self::Added(degree), ADDED_DEGREES, "add"),
+        };
+        if !degrees.contains(&degree) {
+            let message = format!("cannot {verb} the {}", ordinal(degree));
+            return Err((written.len(), message));
+        }
+        Ok((alteration, rest))
+    }
+
+    /// Interval of the tone above the root, e.g. a minor 9th for `b9`
+    fn interval(&self) -> Result<Interval, String> {
+        let (accidental, degree) = match *self {
+            Self::Altered(accidental, degree) => (Some(accidental), degree),
+            Self::Added(degree) | Self::Suspended(degree) => (None, degree),
+        };
+        let perfect = matches!((degree - 1) % 7, 0 | 3 | 4);
+        let quality = match (accidental, perfect) {
+            (None, true) => IntervalQuality::Perfect,
+            (None, false) => IntervalQuality::Major,
+            (Some(Accidental::Flat), true) => IntervalQuality::Diminished,
+            (Some(Accidental::Flat), false) => IntervalQuality::Minor,
+            (Some(_), _) => IntervalQuality::Augmented,
+        };
+        Interval::from_quality(quality, degree)
+    }
+
+    /// Writes the alteration in the given notation, without `add` in braces
+    fn fmt_notation(&self, notation: Notation, braced: bool) -> String {
+        match self {
+            Self::Altered(accidental, degree) => {
+                format!("{}{degree}", accidental.symbol(notation))
+            }
+            Self::Added(degree) if braced => degree.to_string(),
+            Self::Added(degree) => format!("add{degree}"),
+            Self::Suspended(degree) => format!("sus{degree}"),
+        }
+    }
+}
+
+impl fmt::Display for Alteration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.fmt_notation(Notation::Ascii, false))
+    }
+}
+
+/// A chord symbol that could not be parsed, with the byte range of the offending part
+#[derive(Debug, PartialEq, Clone)]
+pub struct ChordError {
+    pub span: Range<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ChordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ChordError {}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct Chord {
     pub root: Note,
     pub quality: ChordQuality,
+    /// Extension number following the quality, e.g. the `7` in `Cm7`
+    pub extension: Option<u8>,
+    /// Tones altered or added after the extension, e.g. `b9` and `#11` in `C7b9#11`
+    pub alterations: Vec<Alteration>,
+    /// Tones written in braces, e.g. `b5` in `C7{b5}`, or `6` and `9` in `C7{6,9}`
+    pub voicing: Vec<Alteration>,
+    /// Bass note of a slash chord, e.g. the `G` in `C/G`
+    pub bass: Option<Note>,
 }
 
 impl Chord {
     pub fn new(s: &str) -> Result<Self, String> {
-        Ok(Chord {
-            root: Note {
-                pitch: PitchClass::C,
-                accidental: None,
-            },
-            quality: ChordQuality::Major,
+        Self::parse(s).map_err(|err| err.to_string())
+    }
+
+    /// Parses a chord symbol such as `Ebm9/Bb`, `C7b9#11`, `C6/9` or `C7{6,9}`,
+    /// pointing at the part of it that is not understood when it fails
+    ///
+    /// A symbol is a root note, a quality, an extension, alterations, tones in braces
+    /// and a bass note, all but the root being optional
+    pub fn parse(s: &str) -> Result<Self, ChordError> {
+        let error = |start: usize, len: usize, message: String| ChordError {
+            span: start..start + len,
+            message: format!("{message} in '{s}'"),
+        };
+        // Where `rest` starts in `s`
+        let at = |rest: &str| s.len() - rest.len();
+
+        let (root, rest) = split_note(s).map_err(|message| ChordError {
+            span: 0..s.chars().next().map_or(0, char::len_utf8),
+            message,
+        })?;
+
+        let (symbol, quality, rest) = split_quality(rest);
+
+        let (digits, mut rest) = split_number(rest);
+        let extension = if digits.is_empty() {
+            None
+        } else {
+            match digits.parse::<u8>() {
+                Ok(n @ 1..=13) if !digits.starts_with('0') => Some(n),
+                _ => {
+                    let message = format!("unknown chord extension '{digits}'");
+                    return Err(error(at(rest) - digits.len(), digits.len(), message));
+                }
+            }
+        };
+        // The parenthesis of e.g. `Cm(maj7)` closes after the extension
+        if symbol.contains('(') {
+            rest = rest
+                .strip_prefix(')')
+                .ok_or_else(|| error(at(rest), 0, "missing ')'".to_string()))?;
+        }
+        // A triangle on its own stands for the major 7th, e.g. `CΔ`, and `ø` for the minor 7th
+        let seventh = match quality {
+            ChordQuality::MajorSeventh | ChordQuality::MinorMajor => symbol.ends_with('Δ'),
+            ChordQuality::HalfDiminished => true,
+            _ => false,
+        };
+        let extension = if seventh {
+            extension.or(Some(7))
+        } else {
+            extension
+        };
+
+        let mut alterations = Vec::new();
+        if extension == Some(6) {
+            if let Some(after) = rest.strip_prefix("/9") {
+                alterations.push(Alteration::Added(9));
+                rest = after;
+            }
+        }
+        while !rest.is_empty() && !rest.starts_with(['{', '/']) {
+            let (alteration, after) = Alteration::split(rest, false)
+                .map_err(|(len, message)| error(at(rest), len, message))?;
+            alterations.push(alteration);
+            rest = after;
+        }
+
+        // A minor 7th (or higher) with a flat 5th is half-diminished, e.g. `Cm7b5` is `Cø7`
+        let mut quality = quality;
+        let flat_fifth = Alteration::Altered(Accidental::Flat, 5);
+        if quality == ChordQuality::Minor && matches!(extension, Some(7 | 9 | 11 | 13)) {
+            if let Some(i) = alterations.iter().position(|a| *a == flat_fifth) {
+                alterations.remove(i);
+                quality = ChordQuality::HalfDiminished;
+            }
+        }
+
+        let mut voicing = Vec::new();
+        if let Some(braced) = rest.strip_prefix('{') {
+            let Some(end) = braced.find('}') else {
+                return Err(error(at(rest), rest.len(), "missing '}'".to_string()));
+            };
+            let mut start = at(braced);
+            for tone in braced[..end].split(',') {
+                let trimmed = tone.trim();
+                let tone_start = start + tone.len() - tone.trim_start().len();
+                match Alteration::split(trimmed, true) {
+                    Ok((alteration, "")) => voicing.push(alteration),
+                    Ok((_, after)) => {
+                        let after_start = tone_start + trimmed.len() - after.len();
+                        let message = format!("unexpected '{after}'");
+                        return Err(error(after_start, after.len(), message));
+                    }
+                    Err((len, message)) => return Err(error(tone_start, len, message)),
+                }
+                start += tone.len() + 1;
+            }
+            rest = &braced[end + 1..];
+        }
+
+        let bass = match rest.strip_prefix('/') {
+            Some(bass) => {
+                Some(parse_note(bass).map_err(|message| error(at(bass), bass.len(), message))?)
+            }
+            None if rest.is_empty() => None,
+            None => return Err(error(at(rest), rest.len(), format!("unexpected '{rest}'"))),
+        };
+
+        Ok(Self {
+            root,
+            quality,
+            extension,
+            alterations,
+            voicing,
+            bass,
         })
     }
 }
 
-// Chord Degree (European: C - B, Roman: I - VII)
+/// Splits a leading quality symbol off of `s`, returning the symbol as written,
+/// the quality and the rest of `s`
+///
+/// A minor-major 7th is matched first, e.g. `mMaj`, `mM`, `-Δ` or `m(maj`,
+/// then a major 7th before a minor, so that e.g. `maj` is not read as `m`.
+/// A major 7th symbol only stands for a major 7th before a 7, 9, 11 or 13,
+/// or as a triangle on its own, so `CM` and `Cmaj` are plain major triads
+fn split_quality(s: &str) -> (&str, ChordQuality, &str) {
+    let prefix = |symbols: &[&str], s: &str| {
+        symbols
+            .iter()
+            .find(|symbol| s.starts_with(*symbol))
+            .map_or(0, |symbol| symbol.len())
+    };
+    let is_seventh = |symbol: &str, rest: &str| match split_number(rest).0 {
+        "7" | "9" | "11" | "13" => true,
+        "" => symbol.ends_with('Δ'),
+        _ => false,
+    };
+    let minor = prefix(MINOR_SYMBOLS, s);
+    if minor > 0 {
+        let open = usize::from(s[minor..].starts_with('('));
+        let major = prefix(MAJOR_SEVENTH_SYMBOLS, &s[minor + open..]);
+        let len = minor + open + major;
+        if major > 0 && is_seventh(&s[minor + open..len], &s[len..]) {
+            return (&s[..len], ChordQuality::MinorMajor, &s[len..]);
+        }
+    }
+    let len = prefix(MAJOR_SEVENTH_SYMBOLS, s);
+    if len > 0 {
+        let quality = if is_seventh(&s[..len], &s[len..]) {
+            ChordQuality::MajorSeventh
+        } else {
+            ChordQuality::Major
+        };
+        return (&s[..len], quality, &s[len..]);
+    }
+    if minor > 0 {
+        // A major 7th symbol without a 7th is left out, e.g. `CmM` is a minor triad
+        let len = minor + prefix(MAJOR_SEVENTH_SYMBOLS, &s[minor..]);
+        return (&s[..len], ChordQuality::Minor, &s[len..]);
+    }
+    QUALITY_SYMBOLS
+        .iter()
+        .find_map(|(symbol, quality)| {
+            s.strip_prefix(symbol)
+                .map(|rest| (&s[..symbol.len()], *quality, rest))
+        })
+        .unwrap_or(("", ChordQuality::Major, s))
+}
+
+/// Splits the leading ASCII digits off of `s`
+fn split_number(s: &str) -> (&str, &str) {
+    s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()))
+}
+
+/// Splits a leading note (letter plus optional accidental, e.g. `bb` or `x`) off of `s`
+fn split_note(s: &str) -> Result<(Note, &str), String> {
+    if !s.is_char_boundary(1) {
+        return Err("unknown pitch".to_string());
+    }
+    let accidental = ACCIDENTAL_SYMBOLS
+        .iter()
+        .find(|symbol| s[1..].starts_with(*symbol))
+        .map_or(0, |symbol| symbol.len());
+    let end = 1 + accidental;
+    Ok((Note::new(&s[..end])?, &s[end..]))
+}
 
-// Chord Quality: Major, Minor, 7ths, Aug
+fn parse_note(s: &str) -> Result<Note, String> {
+    match split_note(s)? {
+        (note, "") => Ok(note),
+        (_, rest) => Err(format!("unexpected '{rest}' after note")),
+    }
+}
 
-// Chord Inversions: Root, 1st, 2nd
+/// Ordinal number of a degree, e.g. `3rd` or `11th`
+fn ordinal(n: u8) -> String {
+    let suffix = match n {
+        n if (11..=13).contains(&(n % 100)) => "th",
+        n if n % 10 == 1 => "st",
+        n if n % 10 == 2 => "nd",
+        n if n % 10 == 3 => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
 
-// Transposition
+/// A note of a spelled chord, along with its role in the chord
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ChordTone {
+    pub note: Note,
+    /// Interval above the root, e.g. a minor 3rd in a minor chord
+    pub interval: Interval,
+    /// Whether the note is the bass note of a slash chord
+    pub bass: bool,
+}
+
+impl ChordTone {
+    /// Name of the chord tone, e.g. `root`, `3rd`, `5th` or `9th`
+    pub fn role(&self) -> String {
+        match self.interval.number() {
+            1 => "root".to_string(),
+            n => ordinal(n),
+        }
+    }
+}
+
+impl fmt::Display for ChordTone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.note, self.role())
+    }
+}
+
+impl Chord {
+    /// Intervals above the root that make up the chord
+    fn intervals(&self) -> Result<Vec<Interval>, String> {
+        let triad = match (self.quality, self.extension) {
+            (ChordQuality::Sus, Some(2)) => vec!["P1", "M2", "P5"],
+            (ChordQuality::Sus, Some(4) | None) => vec!["P1", "P4", "P5"],
+            (ChordQuality::Sus, Some(n)) => {
+                return Err(format!("cannot suspend the {}", ordinal(n)))
+            }
+            (ChordQuality::Major, Some(5)) => vec!["P1", "P5"],
+            (ChordQuality::Major | ChordQuality::MajorSeventh, _) => vec!["P1", "M3", "P5"],
+            (ChordQuality::Minor | ChordQuality::MinorMajor, _) => vec!["P1", "m3", "P5"],
+            (ChordQuality::Diminished | ChordQuality::HalfDiminished, _) => {
+                vec!["P1", "m3", "d5"]
+            }
+            (ChordQuality::Augmented, _) => vec!["P1", "M3", "A5"],
+        };
+        let seventh = match self.quality {
+            ChordQuality::MajorSeventh | ChordQuality::MinorMajor => "M7",
+            ChordQuality::Diminished => "d7",
+            _ => "m7",
+        };
+        let extensions: &[&str] = match (self.quality, self.extension) {
+            (ChordQuality::HalfDiminished, None) => &[seventh],
+            (ChordQuality::Sus, _) | (_, None | Some(5)) => &[],
+            (_, Some(2)) => &["M2"],
+            (_, Some(4)) => &["P4"],
+            (_, Some(6)) => &["M6"],
+            (_, Some(7)) => &[seventh],
+            (_, Some(9)) => &[seventh, "M9"],
+            (_, Some(11)) => &[seventh, "M9", "P11"],
+            (_, Some(13)) => &[seventh, "M9", "P11", "M13"],
+            (_, Some(n)) => {
+                let quality = self.quality.symbol(&ChordStyle::STANDARD);
+                return Err(format!("cannot spell a {quality}{n} chord"));
+            }
+        };
+        let mut intervals = triad
+            .into_iter()
+            .chain(extensions.iter().copied())
+            .map(Interval::new)
+            .collect::<Result<Vec<_>, String>>()?;
+        // An altered tone replaces the unaltered one, e.g. the 5th of `C7b5`,
+        // and a suspended one replaces the 3rd
+        for alteration in self.alterations.iter().chain(&self.voicing) {
+            let replaced = match alteration {
+                Alteration::Altered(_, degree) => *degree,
+                Alteration::Added(_) => 0,
+                Alteration::Suspended(_) => 3,
+            };
+            intervals.retain(|i| i.number() != replaced);
+            intervals.push(alteration.interval()?);
+        }
+        intervals.sort_by_key(|i| i.semitones());
+        Ok(intervals)
+    }
+
+    /// Spells the chord with correct enharmonic spelling, e.g. `Ebm9` is Eb, Gb, Bb, Db and F
+    ///
+    /// Tones are listed from the root upwards, except that the bass note of a slash chord
+    /// comes first, e.g. `F/A` is A, F and C
+    pub fn notes(&self) -> Result<Vec<ChordTone>, String> {
+        let mut tones: Vec<ChordTone> = Vec::new();
+        for interval in self.intervals()? {
+            let note = self.root.add_interval(&interval)?;
+            // e.g. the 11th of `C13sus4`, which is the suspended 4th an octave up
+            if !tones.iter().any(|t| t.note == note) {
+                tones.push(ChordTone {
+                    note,
+                    interval,
+                    bass: false,
+                });
+            }
+        }
+
+        if let Some(bass) = self.bass {
+            // A natural is the same note as one without an accidental
+            let tone = match tones
+                .iter()
+                .position(|t| t.note.position() == bass.position())
+            {
+                Some(i) => tones.remove(i),
+                None => ChordTone {
+                    note: bass,
+                    interval: Interval::between(&self.root, &bass)?,
+                    bass: true,
+                },
+            };
+            tones.insert(0, ChordTone { bass: true, ..tone });
+        }
+        Ok(tones)
+    }
+}
+
+impl Chord {
+    /// Writes the chord in the given style, e.g. `F♯m7♭5`, `CM7` or `C-7`
+    pub fn fmt_style(&self, style: &ChordStyle) -> String {
+        let notation = style.notation;
+        let mut chord = self.root.fmt_notation(notation);
+        let symbol = self.quality.symbol(style);
+        match self.extension {
+            Some(extension) if symbol.contains('7') => {
+                chord.push_str(&symbol.replacen('7', &extension.to_string(), 1));
+            }
+            Some(extension) => chord.push_str(&format!("{symbol}{extension}")),
+            None => chord.push_str(&symbol),
+        }
+        let mut alterations = self.alterations.iter().peekable();
+        if self.extension == Some(6) && alterations.peek() == Some(&&Alteration::Added(9)) {
+            alterations.next();
+            chord.push_str("/9");
+        }
+        for alteration in alterations {
+            chord.push_str(&alteration.fmt_notation(notation, false));
+        }
+        if !self.voicing.is_empty() {
+            let voicing: Vec<String> = self
+                .voicing
+                .iter()
+                .map(|a| a.fmt_notation(notation, true))
+                .collect();
+            chord.push_str(&format!("{{{}}}", voicing.join(",")));
+        }
+        if let Some(bass) = &self.bass {
+            chord.push_str(&format!("/{}", bass.fmt_notation(notation)));
+        }
+        chord
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.fmt_style(&ChordStyle::STANDARD))
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::pitch::{Note, PitchClass};
+    use crate::pitch::{Accidental, Note, PitchClass};
+
+    fn note(s: &str) -> Note {
+        Note::new(s).unwrap()
+    }
 
     #[test]
     fn parse_root_chord() {
@@ -50,7 +637,331 @@ mod tests {
                     accidental: None
                 },
                 quality: ChordQuality::Major,
+                extension: None,
+                alterations: Vec::new(),
+                voicing: Vec::new(),
+                bass: None,
             })
         );
     }
+
+    #[test]
+    fn parse_chord_qualities() {
+        let quality = |s| Chord::new(s).unwrap().quality;
+        assert_eq!(quality("Cm"), ChordQuality::Minor);
+        assert_eq!(quality("Cmin"), ChordQuality::Minor);
+        assert_eq!(quality("CMaj7"), ChordQuality::MajorSeventh);
+        assert_eq!(quality("CmMaj7"), ChordQuality::MinorMajor);
+        assert_eq!(quality("Csus4"), ChordQuality::Sus);
+        assert_eq!(quality("Cdim7"), ChordQuality::Diminished);
+        assert_eq!(quality("Caug"), ChordQuality::Augmented);
+        assert_eq!(quality("C°7"), ChordQuality::Diminished);
+        assert_eq!(quality("Cø"), ChordQuality::HalfDiminished);
+        assert_eq!(quality("Cø7"), ChordQuality::HalfDiminished);
+        assert_eq!(quality("Cm7b5"), ChordQuality::HalfDiminished);
+        assert_eq!(quality("Cm6b5"), ChordQuality::Minor);
+        assert_eq!(Chord::new("Cø").unwrap(), Chord::new("Cm7b5").unwrap());
+        assert_eq!(Chord::new("Cø9").unwrap(), Chord::new("Cmin9b5").unwrap());
+    }
+
+    #[test]
+    fn parse_double_and_natural_accidentals() {
+        let chord = |s| Chord::new(s).unwrap();
+        assert_eq!(chord("Cbb").root, note("Cbb"));
+        assert_eq!(chord("F##m").root, note("F##"));
+        assert_eq!(chord("Fx7").root, note("F##"));
+        assert_eq!(chord("E♮").root.accidental, Some(Accidental::Natural));
+        assert_eq!(chord("Bbbdim7/Ebb").bass, Some(note("Ebb")));
+        assert_eq!(chord("C/Gx").bass, Some(note("G##")));
+        assert_eq!(chord("Bb").root, note("Bb"));
+        assert_eq!(chord("Cb5").root, note("Cb"));
+    }
+
+    #[test]
+    fn parse_extended_slash_chord() {
+        assert_eq!(
+            Chord::new("Ebm9/Bb"),
+            Ok(Chord {
+                root: Note {
+                    pitch: PitchClass::E,
+                    accidental: Some(Accidental::Flat)
+                },
+                quality: ChordQuality::Minor,
+                extension: Some(9),
+                alterations: Vec::new(),
+                voicing: Vec::new(),
+                bass: Some(note("Bb")),
+            })
+        );
+        assert_eq!(Chord::new("C12").unwrap().extension, Some(12));
+    }
+
+    #[test]
+    fn parse_invalid_chords() {
+        assert!(Chord::new("").is_err());
+        assert!(Chord::new("H7").is_err());
+        assert!(Chord::new("C14").is_err());
+        assert!(Chord::new("Cm7x").is_err());
+        assert!(Chord::new("C/").is_err());
+        assert!(Chord::new("C/Gm").is_err());
+    }
+
+    #[test]
+    fn parse_alterations() {
+        let flat = |degree| Alteration::Altered(Accidental::Flat, degree);
+        let sharp = |degree| Alteration::Altered(Accidental::Sharp, degree);
+        let alterations = |s| Chord::new(s).unwrap().alterations;
+        assert_eq!(alterations("C7b9"), vec![flat(9)]);
+        assert_eq!(alterations("C7#9b13"), vec![sharp(9), flat(13)]);
+        assert_eq!(alterations("Cadd9"), vec![Alteration::Added(9)]);
+        assert_eq!(alterations("C6/9"), vec![Alteration::Added(9)]);
+        assert_eq!(alterations("C13sus4"), vec![Alteration::Suspended(4)]);
+        assert_eq!(alterations("C7sus"), vec![Alteration::Suspended(4)]);
+
+        let chord = Chord::new("Cmaj7#5/E").unwrap();
+        assert_eq!(chord.quality, ChordQuality::MajorSeventh);
+        assert_eq!(chord.extension, Some(7));
+        assert_eq!(chord.alterations, vec![sharp(5)]);
+        assert_eq!(chord.bass, Some(note("E")));
+
+        assert_eq!(Chord::new("C7{b5}").unwrap().voicing, vec![flat(5)]);
+        assert_eq!(
+            Chord::new("C7{6, 9}").unwrap().voicing,
+            vec![Alteration::Added(6), Alteration::Added(9)]
+        );
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_problem() {
+        let error = |s| Chord::parse(s).unwrap_err();
+        assert_eq!(
+            error("Cm7x"),
+            ChordError {
+                span: 3..4,
+                message: "unknown alteration 'x' in 'Cm7x'".to_string()
+            }
+        );
+        assert_eq!(error("C7#3").span, 2..4);
+        assert_eq!(error("C7#3").message, "cannot raise the 3rd in 'C7#3'");
+        assert_eq!(error("Cadd7").message, "cannot add the 7th in 'Cadd7'");
+        assert_eq!(error("C7b").message, "missing degree after 'b' in 'C7b'");
+        assert_eq!(error("C14").span, 1..3);
+        assert_eq!(error("C7{b5").span, 2..5);
+        assert_eq!(error("C7{6, x9}").span, 6..8);
+        assert_eq!(error("C7{6,9b}").span, 6..7);
+        assert_eq!(error("C7{b5}x").span, 6..7);
+        assert_eq!(error("C/Gm").span, 2..4);
+        assert_eq!(error("H7").span, 0..1);
+    }
+
+    #[test]
+    fn display_chord() {
+        for s in [
+            "C", "C#m7", "BbMaj7", "CmMaj7", "Dsus2", "Cdim7", "Ebaug", "C/G", "Ab6/Eb", "C7b9",
+            "C7#11", "Cadd9", "C6/9", "C6/9/E", "C13sus4", "CMaj7#5", "C7{b5}", "C7{6,9}", "Cm7b5",
+            "Cm9b5", "Abbm", "F##7/C##", "E♮",
+        ] {
+            assert_eq!(Chord::new(s).unwrap().to_string(), s);
+        }
+        assert_eq!(Chord::new("Amin7").unwrap().to_string(), "Am7");
+        assert_eq!(Chord::new("Cmaj7").unwrap().to_string(), "CMaj7");
+        assert_eq!(Chord::new("C6add9").unwrap().to_string(), "C6/9");
+        assert_eq!(Chord::new("C°7").unwrap().to_string(), "Cdim7");
+        assert_eq!(Chord::new("Cx").unwrap().to_string(), "C##");
+        assert_eq!(Chord::new("Cø").unwrap().to_string(), "Cm7b5");
+        assert_eq!(Chord::new("Cø9").unwrap().to_string(), "Cm9b5");
+    }
+
+    #[test]
+    fn display_chord_in_typographic_notation() {
+        for (ascii, typographic) in [
+            ("C#m7b5", "C♯m7♭5"),
+            ("Bb7#9{b13}/Ab", "B♭7♯9{♭13}/A♭"),
+            ("F##dim7/Dbb", "F𝄪dim7/D𝄫"),
+            ("E♮", "E♮"),
+        ] {
+            let chord = Chord::new(ascii).unwrap();
+            let style = ChordStyle {
+                notation: Notation::Typographic,
+                ..Default::default()
+            };
+            assert_eq!(chord.fmt_style(&style), typographic);
+            assert_eq!(Chord::new(typographic), Ok(chord));
+        }
+    }
+
+    #[test]
+    fn parse_quality_spellings() {
+        let quality = |s| Chord::new(s).unwrap().quality;
+        for s in ["CMaj7", "Cmaj7", "CM7", "CΔ7", "CΔ"] {
+            assert_eq!(quality(s), ChordQuality::MajorSeventh, "{s}");
+        }
+        for s in ["Cm", "Cmin", "C-", "C-7"] {
+            assert_eq!(quality(s), ChordQuality::Minor, "{s}");
+        }
+        for s in [
+            "CmMaj7", "Cmmaj7", "CmM7", "CminMaj7", "C-Δ7", "CmΔ", "Cm(maj7)", "Cm(Maj9)", "CmM9",
+        ] {
+            assert_eq!(quality(s), ChordQuality::MinorMajor, "{s}");
+        }
+        for s in ["CM", "Cmaj", "CMaj", "CM6", "CΔ6", "CMadd9"] {
+            assert_eq!(quality(s), ChordQuality::Major, "{s}");
+        }
+        assert_eq!(quality("CmM"), ChordQuality::Minor);
+        assert_eq!(Chord::new("CmM7"), Chord::new("Cm(maj7)"));
+        assert_eq!(Chord::new("CmM7").unwrap().extension, Some(7));
+        assert_eq!(
+            Chord::new("Cm(maj7").unwrap_err(),
+            "missing ')' in 'Cm(maj7'"
+        );
+        assert_eq!(Chord::new("C-Δ").unwrap().extension, Some(7));
+        assert_eq!(Chord::new("CM").unwrap().extension, None);
+        assert_eq!(Chord::new("CΔ6").unwrap().extension, Some(6));
+        assert_eq!(Chord::new("CM").unwrap().to_string(), "C");
+    }
+
+    #[test]
+    fn display_chord_in_style() {
+        let chords = ["CMaj7", "Cm", "Cdim", "Caug", "CmMaj7", "F#m7b5", "Bb+7"];
+        let styled = |style: &ChordStyle| -> Vec<String> {
+            chords
+                .iter()
+                .map(|s| Chord::new(s).unwrap().fmt_style(style))
+                .collect()
+        };
+        assert_eq!(
+            styled(&ChordStyle::STANDARD),
+            ["CMaj7", "Cm", "Cdim", "Caug", "CmMaj7", "F#m7b5", "Bbaug7"]
+        );
+        assert_eq!(
+            styled(&ChordStyle::SPELLED),
+            ["Cmaj7", "Cmin", "Cdim", "Caug", "Cminmaj7", "F#min7b5", "Bbaug7"]
+        );
+        assert_eq!(
+            styled(&ChordStyle::SHORT),
+            ["CM7", "Cm", "C°", "C+", "CmM7", "F#ø7", "Bb+7"]
+        );
+        assert_eq!(
+            styled(&ChordStyle::JAZZ),
+            ["CΔ7", "C-", "C°", "C+", "C-Δ7", "F#ø7", "Bb+7"]
+        );
+        for style in [ChordStyle::SPELLED, ChordStyle::SHORT, ChordStyle::JAZZ] {
+            for s in chords {
+                let chord = Chord::new(s).unwrap();
+                assert_eq!(Chord::new(&chord.fmt_style(&style)), Ok(chord));
+            }
+        }
+    }
+
+    #[test]
+    fn ascii_styles_round_trip() {
+        for s in ["Cø7", "Cø", "Cø9", "F#m7b5/E", "CmMaj7", "C°7", "C+"] {
+            let chord = Chord::new(s).unwrap();
+            for style in [ChordStyle::STANDARD, ChordStyle::SPELLED] {
+                let written = chord.fmt_style(&style);
+                assert!(written.is_ascii(), "{written}");
+                assert_eq!(Chord::new(&written), Ok(chord.clone()), "{written}");
+            }
+        }
+    }
+
+    #[test]
+    fn parse_typographic_symbols() {
+        let chord = |s| Chord::new(s).unwrap().to_string();
+        assert_eq!(chord("F♯m"), "F#m");
+        assert_eq!(chord("B♭7♭9"), "Bb7b9");
+        assert_eq!(chord("C𝄪/A𝄫"), "C##/Abb");
+        assert_eq!(chord("CΔ"), "CMaj7");
+        assert_eq!(chord("CΔ9"), "CMaj9");
+        assert_eq!(chord("CmΔ7"), "CmMaj7");
+        assert_eq!(chord("Cø7"), "Cm7b5");
+        assert_eq!(chord("C°"), "Cdim");
+        assert_eq!(chord("C+"), "Caug");
+        assert_eq!(chord("G+7"), "Gaug7");
+    }
+
+    fn spell(s: &str) -> Vec<String> {
+        Chord::new(s)
+            .unwrap()
+            .notes()
+            .unwrap()
+            .iter()
+            .map(|t| t.note.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn spell_triads() {
+        assert_eq!(spell("C"), vec!["C", "E", "G"]);
+        assert_eq!(spell("Ebm"), vec!["Eb", "Gb", "Bb"]);
+        assert_eq!(spell("Bdim"), vec!["B", "D", "F"]);
+        assert_eq!(spell("Caug"), vec!["C", "E", "G#"]);
+        assert_eq!(spell("Dsus"), vec!["D", "G", "A"]);
+        assert_eq!(spell("Dsus2"), vec!["D", "E", "A"]);
+        assert_eq!(spell("G5"), vec!["G", "D"]);
+    }
+
+    #[test]
+    fn spell_extended_chords() {
+        assert_eq!(spell("G7"), vec!["G", "B", "D", "F"]);
+        assert_eq!(spell("BbMaj7"), vec!["Bb", "D", "F", "A"]);
+        assert_eq!(spell("C#dim7"), vec!["C#", "E", "G", "Bb"]);
+        assert_eq!(spell("CmMaj7"), vec!["C", "Eb", "G", "B"]);
+        assert_eq!(spell("Am6"), vec!["A", "C", "E", "F#"]);
+        assert_eq!(spell("Ebm9"), vec!["Eb", "Gb", "Bb", "Db", "F"]);
+        assert_eq!(spell("F#11"), vec!["F#", "A#", "C#", "E", "G#", "B"]);
+        assert_eq!(
+            Chord::new("Csus7").unwrap().notes().unwrap_err(),
+            "cannot suspend the 7th"
+        );
+        assert_eq!(spell("C13"), vec!["C", "E", "G", "Bb", "D", "F", "A"]);
+        assert!(Chord::new("C10").unwrap().notes().is_err());
+    }
+
+    #[test]
+    fn spell_altered_chords() {
+        assert_eq!(spell("C7b9"), vec!["C", "E", "G", "Bb", "Db"]);
+        assert_eq!(spell("C7#11"), vec!["C", "E", "G", "Bb", "F#"]);
+        assert_eq!(spell("Cadd9"), vec!["C", "E", "G", "D"]);
+        assert_eq!(spell("C6/9"), vec!["C", "E", "G", "A", "D"]);
+        assert_eq!(spell("C13sus4"), vec!["C", "F", "G", "Bb", "D", "A"]);
+        assert_eq!(spell("Cmaj7#5"), vec!["C", "E", "G#", "B"]);
+        assert_eq!(spell("C7{b5}"), vec!["C", "E", "Gb", "Bb"]);
+        assert_eq!(spell("C7{6,9}"), vec!["C", "E", "G", "A", "Bb", "D"]);
+        assert_eq!(spell("Cm7b5"), vec!["C", "Eb", "Gb", "Bb"]);
+        assert_eq!(spell("Cø"), vec!["C", "Eb", "Gb", "Bb"]);
+        assert_eq!(spell("Cø7"), vec!["C", "Eb", "Gb", "Bb"]);
+        assert_eq!(spell("B°"), vec!["B", "D", "F"]);
+        assert_eq!(spell("Cbb"), vec!["Cbb", "Ebb", "Gbb"]);
+        assert_eq!(spell("Fx"), vec!["F##", "A##", "C##"]);
+    }
+
+    #[test]
+    fn spell_slash_chords() {
+        assert_eq!(spell("F/A"), vec!["A", "F", "C"]);
+        assert_eq!(spell("C/D"), vec!["D", "C", "E", "G"]);
+        assert_eq!(spell("C/E♮"), vec!["E", "C", "G"]);
+        let tones = Chord::new("F/A").unwrap().notes().unwrap();
+        assert!(tones[0].bass);
+        assert_eq!(tones[0].role(), "3rd");
+    }
+
+    #[test]
+    fn chord_tone_roles() {
+        let roles = |s| -> Vec<String> {
+            Chord::new(s)
+                .unwrap()
+                .notes()
+                .unwrap()
+                .iter()
+                .map(|t| t.role())
+                .collect()
+        };
+        assert_eq!(roles("Cm9"), vec!["root", "3rd", "5th", "7th", "9th"]);
+        assert_eq!(roles("Csus2"), vec!["root", "2nd", "5th"]);
+        assert_eq!(
+            roles("C11"),
+            vec!["root", "3rd", "5th", "7th", "9th", "11th"]
+        );
+    }
 }
@@ -0,0 +1,137 @@
+//! Errors about a song, pointing at where they are in its source
+
+use crate::text::display_width;
+use std::fmt;
+use std::ops::Range;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Severity {
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found in a song, e.g. an unknown chord
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    /// Byte range of the offending text in the source
+    pub span: Range<usize>,
+    /// Line of the start of the span, counting from 1
+    pub line: usize,
+    /// Column of the start of the span in characters, counting from 1
+    pub column: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// An error about the text at `span` of `source`
+    pub fn error(source: &str, span: Range<usize>, message: impl Into<String>) -> Self {
+        Self::new(source, span, Severity::Error, message)
+    }
+
+    fn new(
+        source: &str,
+        span: Range<usize>,
+        severity: Severity,
+        message: impl Into<String>,
+    ) -> Self {
+        let before = &source[..span.start];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Self {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            span,
+            severity,
+            message: message.into(),
+        }
+    }
+
+    /// Formats the diagnostic with the line of `source` it points at,
+    /// and carets under the offending text
+    ///
+    /// ```text
+    /// error: Chord error: unknown chord "H"
+    ///  --> song.cho:1:4
+    ///   |
+    /// 1 | I [H]looked over
+    ///   |    ^
+    /// ```
+    pub fn render(&self, source: &str, path: &str) -> String {
+        let line_start = source[..self.span.start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[self.span.start..]
+            .find(['\r', '\n'])
+            .map_or(source.len(), |i| self.span.start + i);
+        let text = &source[line_start..line_end];
+        let underlined = &source[self.span.start..self.span.end.clamp(self.span.start, line_end)];
+
+        let number = self.line.to_string();
+        let gutter = " ".repeat(number.len());
+        let indent = " ".repeat(display_width(&source[line_start..self.span.start]));
+        let carets = "^".repeat(display_width(underlined).max(1));
+        format!(
+            "{}: {}\n{gutter}--> {path}:{}:{}\n{gutter} |\n{number} | {text}\n{gutter} | {indent}{carets}\n",
+            self.severity, self.message, self.line, self.column
+        )
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.line, self.column, self.severity, self.message
+        )
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_and_column() {
+        let source = "[C]Hello\n파이팅 [H]there";
+        let diagnostic = Diagnostic::error(source, 20..21, "unknown chord");
+        assert_eq!(diagnostic.line, 2);
+        assert_eq!(diagnostic.column, 6);
+        assert_eq!(diagnostic.to_string(), "2:6: error: unknown chord");
+
+        let diagnostic = Diagnostic::error(source, 0..3, "unknown chord");
+        assert_eq!((diagnostic.line, diagnostic.column), (1, 1));
+    }
+
+    #[test]
+    fn test_render() {
+        let source = "{title: Hi}\n파이팅 [Hm]there\n";
+        let diagnostic = Diagnostic::error(source, 23..25, "Chord error: unknown chord \"Hm\"");
+        assert_eq!(
+            diagnostic.render(source, "song.cho"),
+            r#"error: Chord error: unknown chord "Hm"
+ --> song.cho:2:6
+  |
+2 | 파이팅 [Hm]there
+  |         ^^
+"#
+        );
+    }
+
+    #[test]
+    fn test_render_empty_span_at_end() {
+        let source = "[C";
+        let diagnostic = Diagnostic::error(source, 2..2, "Chord error: missing ']'");
+        assert_eq!(
+            diagnostic.render(source, "-"),
+            "error: Chord error: missing ']'\n --> -:1:3\n  |\n1 | [C\n  |   ^\n"
+        );
+    }
+}
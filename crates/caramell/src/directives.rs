@@ -0,0 +1,351 @@
+//! ChordPro directives, e.g. `{title: Swing Low Sweet Chariot}` or `{start_of_chorus}`
+//!
+//! See <https://www.chordpro.org/chordpro/chordpro-directives/>
+
+use std::fmt;
+
+/// Abbreviated directive names and the full names they stand for
+const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("t", "title"),
+    ("st", "subtitle"),
+    ("c", "comment"),
+    ("ci", "comment_italic"),
+    ("cb", "comment_box"),
+    ("soc", "start_of_chorus"),
+    ("eoc", "end_of_chorus"),
+    ("sov", "start_of_verse"),
+    ("eov", "end_of_verse"),
+    ("sob", "start_of_bridge"),
+    ("eob", "end_of_bridge"),
+    ("sot", "start_of_tab"),
+    ("eot", "end_of_tab"),
+    ("sog", "start_of_grid"),
+    ("eog", "end_of_grid"),
+    ("np", "new_page"),
+    ("npp", "new_physical_page"),
+    ("colb", "column_break"),
+    ("col", "columns"),
+];
+
+const METADATA: &[&str] = &[
+    "title",
+    "sorttitle",
+    "subtitle",
+    "artist",
+    "composer",
+    "lyricist",
+    "arranger",
+    "copyright",
+    "album",
+    "year",
+    "key",
+    "time",
+    "tempo",
+    "duration",
+    "capo",
+];
+
+const FORMATTING: &[&str] = &[
+    "new_page",
+    "new_physical_page",
+    "column_break",
+    "columns",
+    "pagetype",
+    "image",
+    "textfont",
+    "textsize",
+    "textcolour",
+    "chordfont",
+    "chordsize",
+    "chordcolour",
+    "tabfont",
+    "tabsize",
+    "tabcolour",
+];
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum CommentStyle {
+    Normal,
+    Italic,
+    Boxed,
+    Highlight,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Directive {
+    /// Song metadata, e.g. `{title: ...}`, `{key: G}` or `{meta: name value}`
+    Metadata { name: String, value: String },
+    /// Start of an environment, e.g. `{start_of_chorus}` or `{sov: Verse 1}`
+    StartOf {
+        environment: String,
+        label: Option<String>,
+    },
+    /// End of an environment, e.g. `{end_of_chorus}`
+    EndOf { environment: String },
+    /// Repeats the most recent chorus, e.g. `{chorus}` or `{chorus: Final Chorus}`
+    Chorus { label: Option<String> },
+    /// A comment shown in the output, e.g. `{comment: Repeat 2x}`
+    Comment { style: CommentStyle, text: String },
+    /// Fonts, sizes, colours and page layout, e.g. `{textsize: 12}` or `{new_page}`
+    Formatting { name: String, value: Option<String> },
+    /// Any directive `caramell` does not know about, preserved as written
+    Unknown { name: String, value: Option<String> },
+}
+
+impl Directive {
+    /// Parses a directive, with or without its surrounding braces
+    ///
+    /// The name and value may be separated by a colon or by whitespace,
+    /// and abbreviated names like `soc` or `c` are expanded
+    pub fn new(s: &str) -> Result<Self, String> {
+        let inner = s.trim();
+        let inner = inner
+            .strip_prefix('{')
+            .and_then(|inner| inner.strip_suffix('}'))
+            .unwrap_or(inner)
+            .trim();
+
+        let (name, value) = match inner.find(|c: char| c == ':' || c.is_whitespace()) {
+            Some(i) => {
+                let value = inner[i..].trim_start_matches(':').trim();
+                (&inner[..i], Some(value).filter(|v| !v.is_empty()))
+            }
+            None => (inner, None),
+        };
+        if name.is_empty() {
+            return Err(format!("missing directive name in '{s}'"));
+        }
+
+        let name = name.to_lowercase();
+        let name = ABBREVIATIONS
+            .iter()
+            .find(|(abbreviation, _)| *abbreviation == name)
+            .map_or(name.as_str(), |(_, full)| full);
+        let value = value.map(|v| v.to_string());
+
+        let directive = if let Some(environment) = name.strip_prefix("start_of_") {
+            // Labels may also be given as an attribute, e.g. {start_of_verse label="Verse 1"}
+            let label = value.map(|label| match label.strip_prefix("label=") {
+                Some(label) => label.trim_matches('"').to_string(),
+                None => label,
+            });
+            Self::StartOf {
+                environment: environment.to_string(),
+                label,
+            }
+        } else if let Some(environment) = name.strip_prefix("end_of_") {
+            Self::EndOf {
+                environment: environment.to_string(),
+            }
+        } else if name == "chorus" {
+            Self::Chorus { label: value }
+        } else if let Some(style) = Self::comment_style(name) {
+            Self::Comment {
+                style,
+                text: value.unwrap_or_default(),
+            }
+        } else if METADATA.contains(&name) {
+            Self::Metadata {
+                name: name.to_string(),
+                value: value.unwrap_or_default(),
+            }
+        } else if name == "meta" {
+            let value = value.unwrap_or_default();
+            let (name, value) = value
+                .split_once(char::is_whitespace)
+                .unwrap_or((&value, ""));
+            Self::Metadata {
+                name: name.to_lowercase(),
+                value: value.trim().to_string(),
+            }
+        } else if FORMATTING.contains(&name) {
+            Self::Formatting {
+                name: name.to_string(),
+                value,
+            }
+        } else {
+            Self::Unknown {
+                name: name.to_string(),
+                value,
+            }
+        };
+        Ok(directive)
+    }
+
+    fn comment_style(name: &str) -> Option<CommentStyle> {
+        match name {
+            "comment" => Some(CommentStyle::Normal),
+            "comment_italic" => Some(CommentStyle::Italic),
+            "comment_box" => Some(CommentStyle::Boxed),
+            "highlight" => Some(CommentStyle::Highlight),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Directive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, value) = match self {
+            Self::Metadata { name, value } if METADATA.contains(&name.as_str()) => {
+                (name.to_string(), Some(value.to_string()))
+            }
+            Self::Metadata { name, value } => ("meta".to_string(), Some(format!("{name} {value}"))),
+            Self::StartOf { environment, label } => {
+                (format!("start_of_{environment}"), label.clone())
+            }
+            Self::EndOf { environment } => (format!("end_of_{environment}"), None),
+            Self::Chorus { label } => ("chorus".to_string(), label.clone()),
+            Self::Comment { style, text } => {
+                let name = match style {
+                    CommentStyle::Normal => "comment",
+                    CommentStyle::Italic => "comment_italic",
+                    CommentStyle::Boxed => "comment_box",
+                    CommentStyle::Highlight => "highlight",
+                };
+                (name.to_string(), Some(text.to_string()))
+            }
+            Self::Formatting { name, value } | Self::Unknown { name, value } => {
+                (name.to_string(), value.clone())
+            }
+        };
+        match value {
+            Some(value) => write!(f, "{{{name}: {value}}}"),
+            None => write!(f, "{{{name}}}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_metadata() {
+        assert_eq!(
+            Directive::new("{title: Swing Low Sweet Chariot}"),
+            Ok(Directive::Metadata {
+                name: "title".to_string(),
+                value: "Swing Low Sweet Chariot".to_string()
+            })
+        );
+        assert_eq!(
+            Directive::new("{t:Hello}"),
+            Ok(Directive::Metadata {
+                name: "title".to_string(),
+                value: "Hello".to_string()
+            })
+        );
+        assert_eq!(
+            Directive::new("{meta: Performer Rick Astley}"),
+            Ok(Directive::Metadata {
+                name: "performer".to_string(),
+                value: "Rick Astley".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn parse_environments() {
+        assert_eq!(
+            Directive::new("{start_of_chorus}"),
+            Ok(Directive::StartOf {
+                environment: "chorus".to_string(),
+                label: None
+            })
+        );
+        assert_eq!(
+            Directive::new("{sov: Verse 1}"),
+            Ok(Directive::StartOf {
+                environment: "verse".to_string(),
+                label: Some("Verse 1".to_string())
+            })
+        );
+        assert_eq!(
+            Directive::new(r#"{start_of_bridge label="Middle 8"}"#),
+            Ok(Directive::StartOf {
+                environment: "bridge".to_string(),
+                label: Some("Middle 8".to_string())
+            })
+        );
+        assert_eq!(
+            Directive::new("{EOC}"),
+            Ok(Directive::EndOf {
+                environment: "chorus".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn parse_chorus_recall() {
+        assert_eq!(
+            Directive::new("{chorus}"),
+            Ok(Directive::Chorus { label: None })
+        );
+        assert_eq!(
+            Directive::new("{chorus: Last time}"),
+            Ok(Directive::Chorus {
+                label: Some("Last time".to_string())
+            })
+        );
+    }
+
+    #[test]
+    fn parse_comments_and_formatting() {
+        assert_eq!(
+            Directive::new("{comment: Chorus}"),
+            Ok(Directive::Comment {
+                style: CommentStyle::Normal,
+                text: "Chorus".to_string()
+            })
+        );
+        assert_eq!(
+            Directive::new("{cb Repeat 2x}"),
+            Ok(Directive::Comment {
+                style: CommentStyle::Boxed,
+                text: "Repeat 2x".to_string()
+            })
+        );
+        assert_eq!(
+            Directive::new("{np}"),
+            Ok(Directive::Formatting {
+                name: "new_page".to_string(),
+                value: None
+            })
+        );
+    }
+
+    #[test]
+    fn parse_unknown_directive() {
+        assert_eq!(
+            Directive::new("{x_custom: some value}"),
+            Ok(Directive::Unknown {
+                name: "x_custom".to_string(),
+                value: Some("some value".to_string())
+            })
+        );
+        assert!(Directive::new("{}").is_err());
+        assert!(Directive::new("{: value}").is_err());
+    }
+
+    #[test]
+    fn display_directive() {
+        for s in [
+            "{title: Swing Low}",
+            "{start_of_chorus}",
+            "{start_of_verse: Verse 1}",
+            "{end_of_verse}",
+            "{comment_italic: Slowly}",
+            "{meta: performer Rick Astley}",
+            "{x_custom: some value}",
+            "{new_page}",
+            "{chorus}",
+            "{chorus: Final Chorus}",
+        ] {
+            assert_eq!(Directive::new(s).unwrap().to_string(), s);
+        }
+        assert_eq!(
+            Directive::new("{soc}").unwrap().to_string(),
+            "{start_of_chorus}"
+        );
+    }
+}
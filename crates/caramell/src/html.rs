@@ -0,0 +1,286 @@
+//! HTML output, for publishing songs on the web
+//!
+//! Each chord is an inline block stacked above the lyrics it is played on, so chords stay over
+//! their syllables in proportional fonts. Sections get the class of their kind, e.g.
+//! `<section class="chorus">`, so they can be styled with [`STYLESHEET`] or any other
+
+use crate::parser::Lines;
+use crate::printing::{self, ChordRows, ChorusRecall, Markup, Options, SectionLine};
+use crate::song::{Section, SectionKind, Song};
+
+/// Styles for the classes used in songs, embedded in standalone pages
+pub const STYLESHEET: &str = r#".song { font-family: system-ui, sans-serif; line-height: 1.3; }
+.song + .song { break-before: page; }
+.song header { text-align: center; margin-bottom: 1.5em; }
+.song h1 { font-size: 1.8em; margin: 0; }
+.song .subtitle { font-size: 1.2em; margin: 0.2em 0; }
+.song .credits { color: #595959; margin: 0.2em 0; }
+.song section { margin: 1.2em 0; }
+.song h2 { font-size: 1em; margin: 0 0 0.5em; }
+.song .chorus > :not(h2) { margin-left: 1.5em; }
+.song .line { white-space: pre-wrap; }
+.song .blank { height: 0.8em; }
+.song .pair { display: inline-block; }
+.song .pair .chord { display: block; padding-right: 0.3em; }
+.song .chord { font-weight: bold; color: #8b1a1a; }
+.song .chords .chord + .chord { margin-left: 1em; }
+.song .lyrics:empty::after { content: "\200b"; }
+.song .comment { color: #595959; margin: 0.3em 0; }
+.song .comment.italic { font-style: italic; }
+.song .comment.boxed span { border: 1px solid; padding: 0 0.3em; }
+.song .comment.highlight span { background: #ffef9e; }
+"#;
+
+/// Escapes `s` so that it is shown exactly as written
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Class of a section of the given kind, e.g. `chorus`, or `intro` for `{start_of_intro}`
+fn class(kind: &SectionKind) -> String {
+    match kind {
+        SectionKind::Body => "body".to_string(),
+        SectionKind::Other(environment) => environment
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '-' })
+            .collect::<String>()
+            .to_lowercase(),
+        kind => kind.name().to_lowercase(),
+    }
+}
+
+/// Formats a song as an `<article>`, e.g. to include in a larger page
+pub fn fmt_song(song: Song, options: &Options) -> String {
+    let options = options.for_song(&song.metadata);
+    let mut fmt_song = String::from("<article class=\"song\">\n");
+    fmt_song.push_str(&fmt_header(&song));
+    for section in song.sections {
+        fmt_song.push_str(&fmt_section(section, &options));
+    }
+    fmt_song.push_str("</article>\n");
+    fmt_song
+}
+
+/// Formats songs as one `<article>` after another
+pub fn fmt_songs(songs: Vec<Song>, options: &Options) -> String {
+    songs
+        .into_iter()
+        .map(|song| fmt_song(song, options))
+        .collect()
+}
+
+/// Formats songs as a standalone page, with [`STYLESHEET`] embedded in it
+pub fn fmt_page(songs: Vec<Song>, options: &Options) -> String {
+    let title = songs
+        .first()
+        .and_then(|song| song.metadata.title.clone())
+        .unwrap_or_else(|| "Songs".to_string());
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{}</title>\n<style>\n{STYLESHEET}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(&title),
+        fmt_songs(songs, options)
+    )
+}
+
+fn fmt_header(song: &Song) -> String {
+    let metadata = &song.metadata;
+    let mut lines = Vec::new();
+    if let Some(title) = &metadata.title {
+        lines.push(format!("<h1>{}</h1>", escape(title)));
+    }
+    for subtitle in &metadata.subtitles {
+        lines.push(format!("<p class=\"subtitle\">{}</p>", escape(subtitle)));
+    }
+    for credit in printing::fmt_credits(metadata) {
+        lines.push(format!("<p class=\"credits\">{}</p>", escape(&credit)));
+    }
+    if lines.is_empty() {
+        return String::new();
+    }
+    format!("<header>\n{}\n</header>\n", lines.join("\n"))
+}
+
+/// Formats a section under its heading, with the class of its kind
+///
+/// A repeated chorus also has the class `recall`, and only its heading unless
+/// [`ChorusRecall::Full`] is used
+pub fn fmt_section(section: Section, options: &Options) -> String {
+    let body = if section.recall && options.chorus_recall == ChorusRecall::Reference {
+        String::new()
+    } else {
+        match section.kind {
+            SectionKind::Tab | SectionKind::Grid => fmt_verbatim(&section.lines),
+            _ => fmt_lines(&section.lines, options),
+        }
+    };
+    // Lines outside of any section may only have been metadata
+    if section.kind == SectionKind::Body && body.is_empty() {
+        return String::new();
+    }
+    let mut classes = class(&section.kind);
+    if section.recall {
+        classes.push_str(" recall");
+    }
+    let mut fmt_section = format!("<section class=\"{classes}\">\n");
+    if section.kind != SectionKind::Body {
+        fmt_section.push_str(&format!("<h2>{}</h2>\n", escape(&section.heading())));
+    }
+    fmt_section.push_str(&body);
+    fmt_section.push_str("</section>\n");
+    fmt_section
+}
+
+/// Formats lines exactly as they were written, in a `<pre>`
+fn fmt_verbatim(lines: &Lines) -> String {
+    let text = printing::fmt_verbatim(lines);
+    let text = text.strip_suffix('\n').unwrap_or(&text);
+    format!("<pre>{}</pre>\n", escape(text))
+}
+
+/// Formats each line of lyrics with its chords as a `<div class="line">`
+fn fmt_lines(lines: &Lines, options: &Options) -> String {
+    let mut fmt_lines = String::new();
+    for line in printing::section_lines(lines, &options.chord_style) {
+        let line = match line {
+            SectionLine::Blank => "<div class=\"blank\"></div>".to_string(),
+            SectionLine::Chords(chords) => {
+                let chords = match options.chord_rows {
+                    ChordRows::Compact => chords,
+                    ChordRows::Bars { .. } => {
+                        vec![printing::fmt_chord_row(&chords, options.chord_rows)]
+                    }
+                };
+                let chords: Vec<String> = chords
+                    .iter()
+                    .map(|chord| format!("<span class=\"chord\">{}</span>", escape(chord)))
+                    .collect();
+                format!("<div class=\"line chords\">{}</div>", chords.join(""))
+            }
+            SectionLine::Lyrics(phrases) => format!(
+                "<div class=\"line\">{}</div>",
+                printing::fmt_phrases(phrases, &options.chord_style, &Html)
+            ),
+            SectionLine::Comment(class, text) => {
+                let class = class.map_or("comment".to_string(), |class| format!("comment {class}"));
+                format!("<p class=\"{class}\"><span>{}</span></p>", escape(text))
+            }
+        };
+        fmt_lines.push_str(&line);
+        fmt_lines.push('\n');
+    }
+    fmt_lines
+}
+
+/// Lyrics as escaped text, with chords stacked above them in inline blocks
+struct Html;
+
+impl Markup for Html {
+    fn lyrics(&self, lyrics: &str) -> String {
+        escape(lyrics)
+    }
+
+    fn chord(&self, chord: &str, word: &str) -> String {
+        format!(
+            "<span class=\"pair\"><span class=\"chord\">{}</span><span class=\"lyrics\">{}</span></span>",
+            escape(chord),
+            escape(word)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::printing::fixtures::{lines, two_songs};
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape(r#"<b>"Rock" & roll</b>"#),
+            "&lt;b&gt;&quot;Rock&quot; &amp; roll&lt;/b&gt;"
+        );
+    }
+
+    #[test]
+    fn test_class() {
+        assert_eq!(class(&SectionKind::Chorus), "chorus");
+        assert_eq!(
+            class(&SectionKind::Other("pre_Chorus".to_string())),
+            "pre-chorus"
+        );
+    }
+
+    #[test]
+    fn test_fmt_lines() {
+        assert_eq!(
+            fmt_lines(
+                &lines("swing [D]low, sweet [G]chari[D]ot\nthe [Bb] end"),
+                &Options::default()
+            ),
+            r#"<div class="line">swing <span class="pair"><span class="chord">D</span><span class="lyrics">low,</span></span> sweet <span class="pair"><span class="chord">G</span><span class="lyrics">chari</span></span><span class="pair"><span class="chord">D</span><span class="lyrics">ot</span></span></div>
+<div class="line">the <span class="pair"><span class="chord">Bb</span><span class="lyrics"></span></span> end</div>
+"#
+        );
+    }
+
+    #[test]
+    fn test_fmt_chord_rows_blank_lines_and_comments() {
+        let song = lines("[C] [G]\n\n{cb: Slowly & softly}");
+        assert_eq!(
+            fmt_lines(&song, &Options::default()),
+            r#"<div class="line chords"><span class="chord">C</span><span class="chord">G</span></div>
+<div class="blank"></div>
+<p class="comment boxed"><span>Slowly &amp; softly</span></p>
+"#
+        );
+        let options = Options {
+            chord_rows: ChordRows::Bars { beats: 2 },
+            ..Default::default()
+        };
+        assert!(fmt_lines(&song, &options).starts_with(
+            r#"<div class="line chords"><span class="chord">| C . | G . |</span></div>"#
+        ));
+    }
+
+    #[test]
+    fn test_fmt_song() {
+        let song = Song::parse(
+            "{title: Swing Low}\n{key: D}\n{soc}\nSwing [D]low\n{eoc}\n{sot}\ne|--<0>--|\n{eot}\n{chorus}"
+                .to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            fmt_song(song, &Options::default()),
+            r#"<article class="song">
+<header>
+<h1>Swing Low</h1>
+<p class="credits">Key: D</p>
+</header>
+<section class="chorus">
+<h2>Chorus</h2>
+<div class="line">Swing <span class="pair"><span class="chord">D</span><span class="lyrics">low</span></span></div>
+</section>
+<section class="tab">
+<h2>Tab</h2>
+<pre>e|--&lt;0&gt;--|</pre>
+</section>
+<section class="chorus recall">
+<h2>Chorus</h2>
+</section>
+</article>
+"#
+        );
+    }
+
+    #[test]
+    fn test_fmt_page() {
+        let page = fmt_page(two_songs(), &Options::default());
+        assert!(page.starts_with("<!DOCTYPE html>"));
+        assert!(page.contains("<title>One</title>"));
+        assert_eq!(page.matches(STYLESHEET).count(), 1);
+        assert_eq!(page.matches("<article class=\"song\">").count(), 2);
+    }
+}
@@ -2,9 +2,10 @@ use logos::Logos;
 
 #[derive(Logos, Debug, PartialEq)]
 pub enum Token {
-    // https://stackoverflow.com/questions/11229080/regex-for-matching-a-music-chord
+    // Anything starting with a note, so that the chord parser can say what is wrong with it,
+    // e.g. C7b9, C6/9 or C7{b5}. Trailing spaces are allowed, as in `[C ]`
     #[regex(
-        r"[A-G](b|#)?(mMaj|Maj|min|m|sus|dim|aug)?(1[0-2]|[1-9])?(/[A-G](b|#)?)?",
+        r"[A-G][^\[\]{}\s]*(\{[^{}\[\]\r\n]*\}[^\[\]{}\s]*)*[ \t]*",
         priority = 2
     )]
     Chord,
@@ -15,11 +16,19 @@ pub enum Token {
     #[token("]")]
     RSqBracket,
 
+    // e.g. {title: Never Gonna Give You Up}
+    #[regex(r"\{[^}\r\n]*\}")]
+    Directive,
+
+    // Comment lines are not part of the song, e.g. # A simple ChordPro song
+    #[regex(r"#[^\r\n]*")]
+    Comment,
+
     #[regex("\n|\r\n")]
     NewLine,
 
-    // TODO: Look into multi-lingual lyric lexer: https://www.regular-expressions.info/unicode.html#prop
-    #[regex("[a-zA-Z -'\",]*")]
+    // Any text in any script, except for the delimiters of chords and directives
+    #[regex(r"[^\[\]{}\r\n]+")]
     Lyrics,
 }
 
@@ -127,6 +136,23 @@ mod tests {
         check("Cbdim7".to_string(), Token::Chord);
     }
 
+    #[test]
+    fn lex_half_diminished_chords() {
+        check("Cø".to_string(), Token::Chord);
+        check("Cø7".to_string(), Token::Chord);
+        check("Cm7b5".to_string(), Token::Chord);
+        check("C°7".to_string(), Token::Chord);
+    }
+
+    #[test]
+    fn lex_double_and_natural_accidentals() {
+        check("Cbb".to_string(), Token::Chord);
+        check("C##m".to_string(), Token::Chord);
+        check("Cx7".to_string(), Token::Chord);
+        check("E♮".to_string(), Token::Chord);
+        check("C/Bbb".to_string(), Token::Chord);
+    }
+
     #[test]
     fn lex_slash_chord() {
         check("C/G".to_string(), Token::Chord);
@@ -134,6 +160,66 @@ mod tests {
         check("Cb/Gb".to_string(), Token::Chord);
     }
 
+    #[test]
+    fn lex_chord_with_trailing_space() {
+        check("C ".to_string(), Token::Chord);
+        check("Am7\t".to_string(), Token::Chord);
+        check("C there".to_string(), Token::Lyrics);
+    }
+
+    #[test]
+    fn lex_altered_chords() {
+        check("C7b9".to_string(), Token::Chord);
+        check("C7#9#11".to_string(), Token::Chord);
+        check("Cadd9".to_string(), Token::Chord);
+        check("C6/9".to_string(), Token::Chord);
+        check("C7{b5}".to_string(), Token::Chord);
+        check("C7{6, 9}/G".to_string(), Token::Chord);
+    }
+
+    #[test]
+    fn lex_directives() {
+        check(
+            "{title: Swing Low Sweet Chariot}".to_string(),
+            Token::Directive,
+        );
+        check("{start_of_chorus}".to_string(), Token::Directive);
+        check("{}".to_string(), Token::Directive);
+    }
+
+    #[test]
+    fn lex_comment() {
+        check("# A simple ChordPro song.".to_string(), Token::Comment);
+        check("#".to_string(), Token::Comment);
+    }
+
+    #[test]
+    fn lex_unclosed_directive() {
+        let mut lex = Token::lexer("{title\n");
+        assert_eq!(lex.next(), Some(Err(())));
+    }
+
+    #[test]
+    fn lex_unicode_lyrics() {
+        check("반복되는 하루에".to_string(), Token::Lyrics);
+        check("Comin’ for to carry me home.".to_string(), Token::Lyrics);
+        check("Señor, ¿qué tal? 1, 2, 3!".to_string(), Token::Lyrics);
+        check("de-sert\tyou".to_string(), Token::Lyrics);
+    }
+
+    #[test]
+    fn lex_lyrics_stop_at_delimiters() {
+        let mut lex = Token::lexer("파[Em]이팅 {c: x}");
+        assert_eq!(lex.next(), Some(Ok(Token::Lyrics)));
+        assert_eq!(lex.slice(), "파");
+        assert_eq!(lex.next(), Some(Ok(Token::LSqBracket)));
+        assert_eq!(lex.next(), Some(Ok(Token::Chord)));
+        assert_eq!(lex.next(), Some(Ok(Token::RSqBracket)));
+        assert_eq!(lex.next(), Some(Ok(Token::Lyrics)));
+        assert_eq!(lex.slice(), "이팅 ");
+        assert_eq!(lex.next(), Some(Ok(Token::Directive)));
+    }
+
     #[test]
     fn lex_chords_and_lyrics() {
         let mut lex = Token::lexer("Never gonna [BbMaj7]give you up");
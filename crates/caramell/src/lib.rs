@@ -1,19 +1,46 @@
-mod chords;
-mod lexer;
-mod parser;
-mod pitch;
-mod printing;
+pub mod chords;
+pub mod diagnostics;
+pub mod directives;
+pub mod html;
+pub mod lexer;
+pub mod markdown;
+pub mod parser;
+#[cfg(feature = "pdf")]
+pub mod pdf;
+pub mod pitch;
+pub mod printing;
+pub mod song;
+pub mod text;
+pub mod transpose;
+pub mod typst;
+
+pub use diagnostics::Diagnostic;
+pub use song::{Metadata, Song};
+use transpose::Transposition;
 
 const HALF_STEP: isize = 1;
 const WHOLE_STEP: isize = HALF_STEP * 2;
 
-pub fn print(song: String) {
-    match parser::parse(song) {
-        Ok(lines) => {
-            // lines.debug_print();
-            let fmt_song = printing::fmt_lyrics_and_chords(lines);
-            println!("{fmt_song}");
-        }
-        Err(err) => eprintln!("error: {err}"),
-    }
+/// Prints `song` with its chords above the lyrics, or returns the first problem found in it
+pub fn print(song: String, options: &printing::Options) -> Result<(), Diagnostic> {
+    let song = Song::parse(song)?;
+    // song.lines.debug_print();
+    let fmt_song = printing::fmt_song(song, options);
+    println!("{fmt_song}");
+    Ok(())
+}
+
+/// Formats `song` with every chord transposed, e.g. by `"-2".parse()` half steps or to the key `"Bb".parse()`
+pub fn transpose(
+    song: String,
+    transposition: &Transposition,
+    options: &printing::Options,
+) -> Result<String, Diagnostic> {
+    let parsed = Song::parse(song.clone())?;
+    // A transposition fails because of the source key, so point at where it was set
+    let transposed = parsed.transpose(transposition).map_err(|message| {
+        let span = transpose::key_span(&parsed.lines).unwrap_or_default();
+        Diagnostic::error(&song, span, message)
+    })?;
+    Ok(printing::fmt_song(transposed, options))
 }
@@ -0,0 +1,292 @@
+//! GitHub-flavored Markdown output, e.g. for pasting songs into a wiki
+//!
+//! The title of a song is a heading and each section a subheading. Chords are either printed
+//! above the lyrics in code blocks, aligned as in plain text, or inline as `**[G]**Somebody`
+
+use crate::parser::Lines;
+use crate::printing::{self, ChordRows, ChorusRecall, Markup, Options, SectionLine};
+use crate::song::{Metadata, Section, SectionKind, Song};
+
+/// Where chords are printed
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub enum Chords {
+    /// Above the lyrics in a code block, so they line up in a monospace font
+    #[default]
+    Above,
+    /// In bold brackets before the lyrics they are played on, e.g. `**[G]**Somebody`
+    Inline,
+}
+
+/// Escapes the characters of `s` that Markdown would otherwise format
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' | '~'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Puts `text` in a fenced code block, with a fence longer than any run of backticks in it
+///
+/// Spaces at the ends of lines, e.g. after the last chord of a line, are left out
+fn code_block(text: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in text.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    let fence = "`".repeat(longest.max(2) + 1);
+    let lines: Vec<&str> = text.trim_end().lines().map(str::trim_end).collect();
+    format!("{fence}\n{}\n{fence}\n", lines.join("\n"))
+}
+
+/// Formats a song with its title as a heading, followed by its credits and sections
+pub fn fmt_song(song: Song, options: &Options, chords: Chords) -> String {
+    let options = options.for_song(&song.metadata);
+    let mut blocks = fmt_header(&song.metadata);
+    for section in song.sections {
+        let section = fmt_section(section, &options, chords);
+        if !section.is_empty() {
+            blocks.push(section);
+        }
+    }
+    blocks.join("\n")
+}
+
+/// Formats songs one after another, separated by horizontal rules
+pub fn fmt_songs(songs: Vec<Song>, options: &Options, chords: Chords) -> String {
+    songs
+        .into_iter()
+        .map(|song| fmt_song(song, options, chords))
+        .collect::<Vec<String>>()
+        .join("\n---\n\n")
+}
+
+/// The title, subtitles and credits of a song, as separate blocks
+fn fmt_header(metadata: &Metadata) -> Vec<String> {
+    let mut blocks = Vec::new();
+    if let Some(title) = &metadata.title {
+        blocks.push(format!("# {}\n", escape(title)));
+    }
+    for subtitle in &metadata.subtitles {
+        blocks.push(format!("_{}_\n", escape(subtitle)));
+    }
+    let credits: Vec<String> = printing::fmt_credits(metadata)
+        .iter()
+        .map(|credit| escape(credit))
+        .collect();
+    if !credits.is_empty() {
+        blocks.push(format!("{}\n", credits.join("\\\n")));
+    }
+    blocks
+}
+
+/// Formats a section under a subheading
+///
+/// A repeated chorus is only printed in full with [`ChorusRecall::Full`],
+/// and lines outside of any section are printed without a subheading
+pub fn fmt_section(section: Section, options: &Options, chords: Chords) -> String {
+    let mut fmt_section = String::new();
+    if section.kind != SectionKind::Body {
+        fmt_section.push_str(&format!("## {}\n\n", escape(&section.heading())));
+    }
+    if section.recall && options.chorus_recall == ChorusRecall::Reference {
+        return fmt_section;
+    }
+    let body = match (&section.kind, chords) {
+        (SectionKind::Tab | SectionKind::Grid, _) => printing::fmt_verbatim(&section.lines)
+            .trim_end()
+            .to_string(),
+        (_, Chords::Above) => printing::fmt_lyrics_and_chords(section.lines, options)
+            .trim_end()
+            .to_string(),
+        (_, Chords::Inline) => fmt_inline(&section.lines, options),
+    };
+    if body.is_empty() {
+        return fmt_section;
+    }
+    match (&section.kind, chords) {
+        (SectionKind::Tab | SectionKind::Grid, _) | (_, Chords::Above) => {
+            fmt_section.push_str(&code_block(&body))
+        }
+        (_, Chords::Inline) => {
+            fmt_section.push_str(&body);
+            fmt_section.push('\n');
+        }
+    }
+    fmt_section
+}
+
+/// Formats lines with their chords inline, as paragraphs separated by blank lines
+fn fmt_inline(lines: &Lines, options: &Options) -> String {
+    let mut paragraphs: Vec<Vec<String>> = vec![Vec::new()];
+    for line in printing::section_lines(lines, &options.chord_style) {
+        let paragraph = paragraphs.last_mut().expect("a paragraph to add to");
+        match line {
+            SectionLine::Blank => paragraphs.push(Vec::new()),
+            SectionLine::Chords(chords) => paragraph.push(match options.chord_rows {
+                ChordRows::Compact => chords
+                    .iter()
+                    .map(|chord| format!("**[{}]**", escape(chord)))
+                    .collect::<Vec<String>>()
+                    .join(" "),
+                ChordRows::Bars { .. } => {
+                    format!("`{}`", printing::fmt_chord_row(&chords, options.chord_rows))
+                }
+            }),
+            SectionLine::Lyrics(phrases) => {
+                let line = printing::fmt_phrases(phrases, &options.chord_style, &Inline);
+                paragraph.push(escape_line_start(&line));
+            }
+            SectionLine::Comment(_, text) => {
+                paragraphs.push(vec![format!("_{}_", escape(text))]);
+                paragraphs.push(Vec::new());
+            }
+        }
+    }
+    paragraphs
+        .into_iter()
+        .filter(|paragraph| !paragraph.is_empty())
+        .map(|paragraph| paragraph.join("\\\n"))
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
+
+/// Escapes the start of a line that Markdown would otherwise read as a list item or a heading,
+/// e.g. `- one`, `+ two`, `3. three` or `=====`
+fn escape_line_start(line: &str) -> String {
+    let text = line.trim_start_matches(' ');
+    let indent = &line[..line.len() - text.len()];
+    let digits = text.len() - text.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if text.starts_with(['-', '+', '=']) {
+        format!("{indent}\\{text}")
+    } else if digits > 0 && text[digits..].starts_with(['.', ')']) {
+        format!("{indent}{}\\{}", &text[..digits], &text[digits..])
+    } else {
+        line.to_string()
+    }
+}
+
+/// Lyrics with each chord in bold brackets before the word it is played on
+struct Inline;
+
+impl Markup for Inline {
+    fn lyrics(&self, lyrics: &str) -> String {
+        escape(lyrics)
+    }
+
+    fn chord(&self, chord: &str, word: &str) -> String {
+        format!("**[{}]**{}", escape(chord), escape(word))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn song(song: &str) -> Song {
+        Song::parse(song.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("#1 *hit* [live]"), r"\#1 \*hit\* \[live\]");
+    }
+
+    #[test]
+    fn test_escape_line_start() {
+        assert_eq!(escape_line_start("- one"), r"\- one");
+        assert_eq!(escape_line_start("  + two"), r"  \+ two");
+        assert_eq!(escape_line_start("1. three"), r"1\. three");
+        assert_eq!(escape_line_start("10) four"), r"10\) four");
+        assert_eq!(escape_line_start("==="), r"\===");
+        assert_eq!(escape_line_start("1999 was"), "1999 was");
+        assert_eq!(escape_line_start("**[G]**- dash"), "**[G]**- dash");
+    }
+
+    #[test]
+    fn test_code_block() {
+        assert_eq!(
+            code_block("C  G  \nHi there\n"),
+            "```\nC  G\nHi there\n```\n"
+        );
+        assert_eq!(code_block("e|-```-|"), "````\ne|-```-|\n````\n");
+    }
+
+    #[test]
+    fn test_fmt_song_with_chords_above() {
+        let song = song(
+            "{title: All Star}\n{artist: Smash Mouth}\n{key: G}\n{sov: Verse 1}\n[G]Somebody once [D]told me\n{eov}",
+        );
+        assert_eq!(
+            fmt_song(song, &Options::default(), Chords::Above),
+            "# All Star
+
+Artist: Smash Mouth\\
+Key: G
+
+## Verse 1
+
+```
+G             D
+Somebody once told me
+```
+"
+        );
+    }
+
+    #[test]
+    fn test_fmt_section_inline() {
+        let song = song(
+            "{soc}\n[G]Somebody once [D]told me\n[C] [G]\n\nthe *world*\n{c: Twice}\n{eoc}\n{chorus}",
+        );
+        let mut sections = song
+            .sections
+            .into_iter()
+            .filter(|section| section.kind == SectionKind::Chorus);
+        assert_eq!(
+            fmt_section(
+                sections.next().unwrap(),
+                &Options::default(),
+                Chords::Inline
+            ),
+            r"## Chorus
+
+**[G]**Somebody once **[D]**told me\
+**[C]** **[G]**
+
+the \*world\*
+
+_Twice_
+"
+        );
+        let options = Options {
+            chorus_recall: ChorusRecall::Full,
+            chord_rows: ChordRows::Bars { beats: 2 },
+            ..Default::default()
+        };
+        assert!(
+            fmt_section(sections.next().unwrap(), &options, Chords::Inline)
+                .contains("told me\\\n`| C . | G . |`\n")
+        );
+    }
+
+    #[test]
+    fn test_fmt_songs() {
+        let songs = vec![
+            song("{title: One}\n[C]Hi"),
+            song("{title: Two}\n{sot}\ne|--0--|\n{eot}"),
+        ];
+        assert_eq!(
+            fmt_songs(songs, &Options::default(), Chords::Inline),
+            "# One\n\n**[C]**Hi\n\n---\n\n# Two\n\n## Tab\n\n```\ne|--0--|\n```\n"
+        );
+    }
+}
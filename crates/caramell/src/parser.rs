@@ -1,24 +1,11 @@
+use crate::chords::Chord;
+use crate::diagnostics::Diagnostic;
+use crate::directives::Directive;
 use crate::lexer::Token;
 use logos::Logos;
 use std::collections::BTreeMap;
 use std::fmt;
-
-#[derive(Debug, Clone, PartialEq)]
-pub struct Chord {
-    pub chord: String,
-}
-
-impl Chord {
-    pub fn new(s: String) -> Self {
-        Self { chord: s }
-    }
-}
-
-impl fmt::Display for Chord {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.chord)
-    }
-}
+use std::ops::Range;
 
 /// A phrase is a subsection of a line of song
 /// One phrase has only 1 or 0 chords
@@ -64,85 +51,257 @@ impl fmt::Display for Phrase {
     }
 }
 
+/// A line of a song
 #[derive(Debug, PartialEq, Clone)]
+pub enum Line {
+    /// Lyrics and chords, split into phrases
+    Lyrics(Vec<Phrase>),
+    /// A directive on its own line, e.g. `{title: Never Gonna Give You Up}`
+    Directive(Directive),
+    /// A `#` comment line, which is not part of the song
+    Comment(String),
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
 pub struct Lines {
-    pub lines: BTreeMap<usize, Vec<Phrase>>,
+    pub lines: BTreeMap<usize, Line>,
+    /// Byte range of each line in the source, without its line break
+    pub spans: BTreeMap<usize, Range<usize>>,
 }
 
 impl Lines {
     pub fn new() -> Self {
         Self {
             lines: BTreeMap::new(),
+            spans: BTreeMap::new(),
         }
     }
 
+    /// Appends a phrase to a line of lyrics, replacing any directive or comment on that line
     pub fn add_phrase(&mut self, line: usize, phrase: Phrase) {
-        if let Some(phrases) = self.lines.get_mut(&line) {
-            phrases.push(phrase);
-        } else {
-            self.lines.insert(line, vec![phrase]);
+        match self.lines.get_mut(&line) {
+            Some(Line::Lyrics(phrases)) => phrases.push(phrase),
+            _ => {
+                self.lines.insert(line, Line::Lyrics(vec![phrase]));
+            }
         }
     }
 
+    pub fn add_line(&mut self, line: usize, content: Line) {
+        self.lines.insert(line, content);
+    }
+
+    /// Byte range of a line in the source, or an empty range if the lines were not parsed
+    pub fn span(&self, line: usize) -> Range<usize> {
+        self.spans.get(&line).cloned().unwrap_or_default()
+    }
+
+    /// Whether the line holds (or will hold) lyrics rather than a directive or comment
+    fn is_lyrics(&self, line: usize) -> bool {
+        matches!(self.lines.get(&line), None | Some(Line::Lyrics(_)))
+    }
+
+    /// All phrases of all lyric lines, in order
+    pub fn phrases(&self) -> impl Iterator<Item = &Phrase> {
+        self.lines.values().flat_map(|line| match line {
+            Line::Lyrics(phrases) => phrases.as_slice(),
+            _ => &[],
+        })
+    }
+
+    pub fn phrases_mut(&mut self) -> impl Iterator<Item = &mut Phrase> {
+        self.lines.values_mut().flat_map(|line| match line {
+            Line::Lyrics(phrases) => phrases.as_mut_slice(),
+            _ => &mut [],
+        })
+    }
+
     pub fn debug_print(&self) {
-        for (line, phrases) in self.lines.iter() {
+        for (line, content) in self.lines.iter() {
             println!("Line {line}:");
-            for p in phrases {
-                println!("  {p}");
+            match content {
+                Line::Lyrics(phrases) => {
+                    for p in phrases {
+                        println!("  {p}");
+                    }
+                }
+                Line::Directive(directive) => println!("  {directive}"),
+                Line::Comment(comment) => println!("  #{comment}"),
             }
         }
     }
 }
 
-pub fn parse(song: String) -> Result<Lines, String> {
+/// Parses a song, stopping at the first problem found in it
+pub fn parse(song: String) -> Result<Lines, Diagnostic> {
+    let (lines, diagnostics) = parse_recovering(song);
+    match diagnostics.into_iter().next() {
+        Some(diagnostic) => Err(diagnostic),
+        None => Ok(lines),
+    }
+}
+
+/// Parses a song, skipping over anything malformed, and returns every problem found in it
+///
+/// A chord that cannot be parsed is dropped, a `[` without its `]` is closed at the end of its
+/// line, and stray text after a directive is ignored, so the rest of the song is still parsed
+pub fn parse_recovering(song: String) -> (Lines, Vec<Diagnostic>) {
     let mut lex = Token::lexer(&song);
     let mut current_phrase = Phrase::empty();
     let mut current_line: usize = 0;
+    let mut line_start: usize = 0;
     let mut lines = Lines::new();
+    let mut diagnostics = Vec::new();
+    // Whether the lexer is between `[` and `]`
+    let mut in_chord = false;
+    let mut chord_start: usize = 0;
+    // Problems between `[` and `]`, which are only reported once the chord is closed,
+    // since an unclosed chord is reported on its own
+    let mut chord_diagnostics = Vec::new();
+    // Whether the rest of the line is ignored after an error
+    let mut skip_line = false;
+    let error = |span: Range<usize>, message: String| Diagnostic::error(&song, span, message);
     loop {
         if let Some(token) = lex.next() {
+            if in_chord && token == Ok(Token::NewLine) {
+                let span = chord_start..lex.span().start;
+                diagnostics.push(error(span, "Chord error: missing ']'".to_string()));
+                chord_diagnostics.clear();
+                in_chord = false;
+            }
+            if skip_line && token != Ok(Token::NewLine) {
+                continue;
+            }
             match token {
-                Ok(Token::Lyrics) => {
-                    current_phrase.lyrics = lex.slice().to_string();
-                    current_phrase.start = lex.span().start;
+                Ok(Token::Chord) if in_chord => {
+                    // Start new phrase
+                    match Chord::parse(lex.slice().trim_end()) {
+                        Ok(chord) => current_phrase.chord = Some(chord),
+                        Err(err) => {
+                            let start = lex.span().start;
+                            let span = start + err.span.start..start + err.span.end;
+                            chord_diagnostics.push(error(span, format!("Chord error: {err}")));
+                        }
+                    }
+                }
+                Ok(Token::RSqBracket) if in_chord => {
+                    diagnostics.append(&mut chord_diagnostics);
+                    in_chord = false;
+                }
+                Ok(_) if in_chord => {
+                    let message = format!("Chord error: unknown chord {:?}", lex.slice());
+                    chord_diagnostics.push(error(lex.span(), message));
+                }
+                Ok(Token::RSqBracket) => {
+                    let message = format!("Token error: unexpected {:?}", lex.slice());
+                    diagnostics.push(error(lex.span(), message));
+                }
+                Ok(Token::Comment)
+                    if current_line_is_empty(&lines, current_line, &current_phrase) =>
+                {
+                    let comment = lex.slice()[1..].to_string();
+                    lines.add_line(current_line, Line::Comment(comment));
+                }
+                // Trailing comment after a directive
+                Ok(Token::Comment) if !lines.is_lyrics(current_line) => {}
+                Ok(Token::Lyrics | Token::Chord) if !lines.is_lyrics(current_line) => {
+                    if !lex.slice().trim().is_empty() {
+                        let message = format!("Directive error: unexpected {:?}", lex.slice());
+                        diagnostics.push(error(lex.span(), message));
+                        skip_line = true;
+                    }
+                }
+                // A `#` in the middle of a line, or a chord name outside of `[]`, is part of the lyrics
+                Ok(Token::Lyrics | Token::Comment | Token::Chord) => {
+                    if current_phrase.lyrics.is_empty() {
+                        current_phrase.start = lex.span().start;
+                    }
+                    current_phrase.lyrics.push_str(lex.slice());
                     current_phrase.end = lex.span().end;
                 }
+                Ok(Token::LSqBracket) if !lines.is_lyrics(current_line) => {
+                    let message = format!("Directive error: unexpected {:?}", lex.slice());
+                    diagnostics.push(error(lex.span(), message));
+                    skip_line = true;
+                }
                 Ok(Token::LSqBracket) => {
                     lines.add_phrase(current_line, current_phrase.clone());
                     current_phrase = Phrase::empty();
+                    in_chord = true;
+                    chord_start = lex.span().start;
                 }
-                Ok(Token::Chord) => {
-                    // Start new phrase
-                    current_phrase.chord = Some(Chord::new(lex.slice().to_string()));
+                Ok(Token::Directive) => {
+                    if !current_line_is_empty(&lines, current_line, &current_phrase) {
+                        let message =
+                            format!("Directive error: {:?} must be on its own line", lex.slice());
+                        diagnostics.push(error(lex.span(), message));
+                        continue;
+                    }
+                    match Directive::new(lex.slice()) {
+                        Ok(directive) => lines.add_line(current_line, Line::Directive(directive)),
+                        Err(err) => {
+                            let message = format!("Directive error: {err}");
+                            diagnostics.push(error(lex.span(), message));
+                            skip_line = true;
+                        }
+                    }
                 }
-                Ok(Token::RSqBracket) => {}
                 Ok(Token::NewLine) => {
-                    lines.add_phrase(current_line, current_phrase.clone());
+                    if lines.is_lyrics(current_line) {
+                        lines.add_phrase(current_line, current_phrase.clone());
+                    }
+                    lines
+                        .spans
+                        .insert(current_line, line_start..lex.span().start);
                     current_phrase = Phrase::empty();
                     current_line += 1;
+                    line_start = lex.span().end;
+                    skip_line = false;
+                }
+                Err(()) => {
+                    let message = format!("Token error: {:?}", lex.slice());
+                    diagnostics.push(error(lex.span(), message));
                 }
-                Err(()) => return Err(format!("Token error: {:?}", lex.slice())),
             };
         } else {
+            if in_chord {
+                let span = chord_start..song.len();
+                diagnostics.push(error(span, "Chord error: missing ']'".to_string()));
+            }
             // Store last phrase
-            lines.add_phrase(current_line, current_phrase.clone());
+            if lines.is_lyrics(current_line) {
+                lines.add_phrase(current_line, current_phrase.clone());
+            }
+            lines.spans.insert(current_line, line_start..song.len());
             break;
         }
     }
-    Ok(lines)
+    (lines, diagnostics)
+}
+
+/// Whether nothing but whitespace has been seen on the current line so far
+fn current_line_is_empty(lines: &Lines, line: usize, phrase: &Phrase) -> bool {
+    !lines.lines.contains_key(&line) && phrase.chord.is_none() && phrase.lyrics.trim().is_empty()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn chord(s: &str) -> Chord {
+        Chord::new(s).unwrap()
+    }
+
     #[test]
     fn test_add_phrase() {
         let mut lines = Lines::new();
         lines.add_phrase(0, Phrase::new("Hi".to_string(), 0, 0, None));
         assert_eq!(
             lines.lines,
-            BTreeMap::from([(0 as usize, vec![Phrase::new("Hi".to_string(), 0, 0, None)])])
+            BTreeMap::from([(
+                0_usize,
+                Line::Lyrics(vec![Phrase::new("Hi".to_string(), 0, 0, None)])
+            )])
         )
     }
 
@@ -153,14 +312,15 @@ mod tests {
             Ok(Lines {
                 lines: BTreeMap::from([(
                     0,
-                    vec![
+                    Line::Lyrics(vec![
                         Phrase::new("".to_string(), 0, 0, None,),
-                        Phrase::new("".to_string(), 0, 0, Some(Chord::new("C".to_string()))),
-                        Phrase::new("".to_string(), 0, 0, Some(Chord::new("G".to_string()))),
-                        Phrase::new("".to_string(), 0, 0, Some(Chord::new("Am".to_string()))),
-                        Phrase::new("".to_string(), 0, 0, Some(Chord::new("F".to_string()))),
-                    ]
-                )])
+                        Phrase::new("".to_string(), 0, 0, Some(chord("C"))),
+                        Phrase::new("".to_string(), 0, 0, Some(chord("G"))),
+                        Phrase::new("".to_string(), 0, 0, Some(chord("Am"))),
+                        Phrase::new("".to_string(), 0, 0, Some(chord("F"))),
+                    ])
+                )]),
+                spans: BTreeMap::from([(0, 0..13)]),
             })
         );
     }
@@ -177,42 +337,207 @@ Never gonna [Fm7]let you [Bbm]down"#
                 lines: BTreeMap::from([
                     (
                         0,
-                        vec![
+                        Line::Lyrics(vec![
                             Phrase::new("Never gonna ".to_string(), 0, 12, None),
-                            Phrase::new(
-                                "give you ".to_string(),
-                                18,
-                                27,
-                                Some(Chord::new("Ebm9".to_string()))
-                            ),
-                            Phrase::new(
-                                "up".to_string(),
-                                31,
-                                33,
-                                Some(Chord::new("Ab".to_string()))
-                            )
-                        ]
+                            Phrase::new("give you ".to_string(), 18, 27, Some(chord("Ebm9"))),
+                            Phrase::new("up".to_string(), 31, 33, Some(chord("Ab")))
+                        ])
                     ),
                     (
                         1,
-                        vec![
+                        Line::Lyrics(vec![
                             Phrase::new("Never gonna ".to_string(), 34, 46, None),
-                            Phrase::new(
-                                "let you ".to_string(),
-                                51,
-                                59,
-                                Some(Chord::new("Fm7".to_string()))
-                            ),
-                            Phrase::new(
-                                "down".to_string(),
-                                64,
-                                68,
-                                Some(Chord::new("Bbm".to_string()))
-                            ),
-                        ]
+                            Phrase::new("let you ".to_string(), 51, 59, Some(chord("Fm7"))),
+                            Phrase::new("down".to_string(), 64, 68, Some(chord("Bbm"))),
+                        ])
                     )
-                ])
+                ]),
+                spans: BTreeMap::from([(0, 0..33), (1, 34..68)]),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_directives_and_comments() {
+        let lines = parse(
+            r#"# A simple ChordPro song.
+{title: Swing Low Sweet Chariot}
+
+{start_of_chorus}
+Swing [D]low, sweet [G]chari[D]ot
+{end_of_chorus}
+{comment: Chorus}"#
+                .to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            lines.lines[&0],
+            Line::Comment(" A simple ChordPro song.".to_string())
+        );
+        assert_eq!(
+            lines.lines[&1],
+            Line::Directive(Directive::new("{title: Swing Low Sweet Chariot}").unwrap())
+        );
+        assert_eq!(
+            lines.lines[&2],
+            Line::Lyrics(vec![Phrase::new("".to_string(), 0, 0, None)])
+        );
+        assert_eq!(
+            lines.lines[&3],
+            Line::Directive(Directive::new("{start_of_chorus}").unwrap())
+        );
+        assert_eq!(lines.phrases().filter(|p| p.chord.is_some()).count(), 3);
+        assert_eq!(
+            lines.lines[&6],
+            Line::Directive(Directive::new("{comment: Chorus}").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_parse_unknown_directive() {
+        let lines = parse("{x_custom: keep me}  \n".to_string()).unwrap();
+        assert_eq!(
+            lines.lines[&0],
+            Line::Directive(Directive::Unknown {
+                name: "x_custom".to_string(),
+                value: Some("keep me".to_string())
             })
         );
     }
+
+    #[test]
+    fn test_parse_hash_in_lyrics() {
+        let lines = parse("Number #one".to_string()).unwrap();
+        assert_eq!(
+            lines.lines[&0],
+            Line::Lyrics(vec![Phrase::new("Number #one".to_string(), 0, 11, None)])
+        );
+    }
+
+    #[test]
+    fn test_parse_directive_must_be_on_its_own_line() {
+        assert!(parse("Hello {title: Hi}".to_string()).is_err());
+        assert!(parse("{title: Hi} Hello".to_string()).is_err());
+        assert!(parse("{title: Hi}[C]".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_parse_unicode_lyrics() {
+        let lines = parse("파[Em]이팅 해야[Dm]지\nDon’t [C]give it up.".to_string()).unwrap();
+        assert_eq!(
+            lines.lines[&0],
+            Line::Lyrics(vec![
+                Phrase::new("파".to_string(), 0, 3, None),
+                Phrase::new("이팅 해야".to_string(), 7, 20, Some(chord("Em"))),
+                Phrase::new("지".to_string(), 24, 27, Some(chord("Dm"))),
+            ])
+        );
+        assert_eq!(
+            lines.lines[&1],
+            Line::Lyrics(vec![
+                Phrase::new("Don’t ".to_string(), 28, 36, None),
+                Phrase::new("give it up.".to_string(), 39, 50, Some(chord("C"))),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_chord_names_in_lyrics() {
+        let lines = parse("[G]A [D]Bee".to_string()).unwrap();
+        let phrases: Vec<&Phrase> = lines.phrases().collect();
+        assert_eq!(phrases[1].chord, Some(chord("G")));
+        assert_eq!(phrases[1].lyrics, "A ");
+        assert_eq!(phrases[2].chord, Some(chord("D")));
+    }
+
+    #[test]
+    fn test_parse_chord_with_trailing_space() {
+        let lines = parse("[C ]Hello [Am7  ]there".to_string()).unwrap();
+        let chords: Vec<&Chord> = lines.phrases().filter_map(|p| p.chord.as_ref()).collect();
+        assert_eq!(chords, vec![&chord("C"), &chord("Am7")]);
+    }
+
+    #[test]
+    fn test_parse_malformed_chords() {
+        let error = |song: &str| {
+            let err = parse(song.to_string()).unwrap_err();
+            (err.message, err.span)
+        };
+        assert_eq!(
+            error("[Hello] there"),
+            ("Chord error: unknown chord \"Hello\"".to_string(), 1..6)
+        );
+        assert_eq!(
+            error("[C there"),
+            ("Chord error: missing ']'".to_string(), 0..8)
+        );
+        assert_eq!(
+            error("[C7#3 there"),
+            ("Chord error: missing ']'".to_string(), 0..11)
+        );
+        assert_eq!(parse_recovering("[C there".to_string()).1.len(), 1);
+        assert_eq!(
+            error("[C\nthere"),
+            ("Chord error: missing ']'".to_string(), 0..2)
+        );
+        assert_eq!(error("[C"), ("Chord error: missing ']'".to_string(), 0..2));
+        assert_eq!(
+            error("Swing [C7#3]low"),
+            (
+                "Chord error: cannot raise the 3rd in 'C7#3'".to_string(),
+                9..11
+            )
+        );
+        assert_eq!(
+            error("C] there"),
+            ("Token error: unexpected \"]\"".to_string(), 1..2)
+        );
+    }
+
+    #[test]
+    fn test_parse_error_position() {
+        let err = parse("Hello\n  [C]x] there".to_string()).unwrap_err();
+        assert_eq!((err.line, err.column), (2, 7));
+        assert_eq!(err.span, 12..13);
+    }
+
+    #[test]
+    fn test_parse_line_spans() {
+        let lines = parse("{title: Hi}\r\n\n[C]Hello".to_string()).unwrap();
+        assert_eq!(lines.span(0), 0..11);
+        assert_eq!(lines.span(1), 13..13);
+        assert_eq!(lines.span(2), 14..22);
+        assert_eq!(lines.span(3), 0..0);
+    }
+
+    #[test]
+    fn test_parse_recovering() {
+        let (lines, diagnostics) = parse_recovering(
+            "[C]Hello [Hm]there]\n{title: Hi} you\nI [G]looked [Am\nSwing [D]low".to_string(),
+        );
+        let messages: Vec<(usize, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.line, d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (1, "Chord error: unknown chord \"Hm\""),
+                (1, "Token error: unexpected \"]\""),
+                (2, "Directive error: unexpected \" you\""),
+                (3, "Chord error: missing ']'"),
+            ]
+        );
+        assert_eq!(diagnostics[3].span, 48..51);
+        let chords: Vec<String> = lines
+            .phrases()
+            .filter_map(|p| p.chord.as_ref().map(|c| c.to_string()))
+            .collect();
+        assert_eq!(chords, vec!["C", "G", "Am", "D"]);
+        assert_eq!(
+            lines.lines[&1],
+            Line::Directive(Directive::new("{title: Hi}").unwrap())
+        );
+    }
 }
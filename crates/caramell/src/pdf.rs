@@ -0,0 +1,1011 @@
+//! PDF output, laid out directly with embedded fonts, so no external typesetter is needed
+//!
+//! Songs flow down the columns of each page, and each song starts on a new page.
+//! A line of chords is always printed in the same column as the lyrics below it,
+//! and a section heading in the same column as the first line of its section
+
+use crate::chords::ChordStyle;
+use crate::parser::{Lines, Phrase};
+use crate::printing::{self, ChorusRecall, Options, SectionLine};
+use crate::song::{Metadata, Section, SectionKind, Song};
+use printpdf::{Color, IndirectFontRef, Mm, PdfDocument, Pt, Rgb};
+use std::fmt;
+use std::str::FromStr;
+use ttf_parser::{Face, GlyphId};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Height of a row of text, as a multiple of its font size
+const LINE_HEIGHT: f32 = 1.25;
+/// Smallest space between two chords, as a multiple of the font size
+const CHORD_GAP: f32 = 0.5;
+/// Indentation of the lines of a chorus, as a multiple of the font size
+const CHORUS_INDENT: f32 = 1.5;
+/// Space above a section, as a multiple of the font size
+const SECTION_SPACING: f32 = 0.8;
+/// Space between two columns, in millimetres
+const COLUMN_GAP: f32 = 8.0;
+
+/// DejaVu Sans, embedded when no other font is given (see `fonts/LICENSE`)
+pub const BUNDLED_FONT: &[u8] = include_bytes!("../fonts/DejaVuSans.ttf");
+/// DejaVu Sans Bold, for chords, headings and titles when no other font is given
+pub const BUNDLED_BOLD_FONT: &[u8] = include_bytes!("../fonts/DejaVuSans-Bold.ttf");
+
+/// Size of the pages, in portrait orientation
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub enum PageSize {
+    /// 210 × 297 mm
+    #[default]
+    A4,
+    /// 8.5 × 11 in
+    Letter,
+    /// Any other size, in millimetres
+    Custom { width: f32, height: f32 },
+}
+
+impl PageSize {
+    /// Width and height of the page, in millimetres
+    fn dimensions(self) -> (f32, f32) {
+        match self {
+            Self::A4 => (210.0, 297.0),
+            Self::Letter => (215.9, 279.4),
+            Self::Custom { width, height } => (width, height),
+        }
+    }
+}
+
+impl FromStr for PageSize {
+    type Err = String;
+
+    /// Parses `a4`, `letter`, or a size in millimetres such as `148x210`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "a4" => Ok(Self::A4),
+            "letter" => Ok(Self::Letter),
+            size => {
+                let error =
+                    || format!("page size must be a4, letter or WIDTHxHEIGHT in mm, not '{s}'");
+                let (width, height) = size.split_once('x').ok_or_else(error)?;
+                let width: f32 = width.trim().parse().map_err(|_| error())?;
+                let height: f32 = height.trim().parse().map_err(|_| error())?;
+                if width <= 0.0 || height <= 0.0 {
+                    return Err(error());
+                }
+                Ok(Self::Custom { width, height })
+            }
+        }
+    }
+}
+
+impl fmt::Display for PageSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::A4 => write!(f, "a4"),
+            Self::Letter => write!(f, "letter"),
+            Self::Custom { width, height } => write!(f, "{width}x{height}"),
+        }
+    }
+}
+
+/// How songs are laid out on the pages
+#[derive(Debug, PartialEq, Clone)]
+pub struct PageOptions {
+    pub page_size: PageSize,
+    /// Space between the edges of the page and the text, in millimetres
+    pub margin: f32,
+    /// Number of columns side by side on each page
+    pub columns: u8,
+    /// Size of the lyrics in points, which headings and titles are scaled from
+    pub font_size: f32,
+}
+
+impl Default for PageOptions {
+    fn default() -> Self {
+        Self {
+            page_size: PageSize::A4,
+            margin: 20.0,
+            columns: 1,
+            font_size: 11.0,
+        }
+    }
+}
+
+/// TrueType or OpenType fonts to embed in the PDF, as the contents of their files
+#[derive(Debug, Clone)]
+pub struct Fonts {
+    regular: Vec<u8>,
+    bold: Vec<u8>,
+}
+
+impl Fonts {
+    /// The font for lyrics, and the one for chords, headings and titles
+    pub fn new(regular: Vec<u8>, bold: Vec<u8>) -> Result<Self, String> {
+        for (name, data) in [("regular", &regular), ("bold", &bold)] {
+            Face::parse(data, 0).map_err(|err| format!("cannot read the {name} font: {err}"))?;
+        }
+        Ok(Self { regular, bold })
+    }
+
+    /// DejaVu Sans and DejaVu Sans Bold, which are built into `caramell`
+    pub fn bundled() -> Self {
+        Self {
+            regular: BUNDLED_FONT.to_vec(),
+            bold: BUNDLED_BOLD_FONT.to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Regular => "regular",
+            Self::Bold => "bold",
+        }
+    }
+}
+
+/// How a piece of text is printed
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum Style {
+    Lyrics,
+    Chord,
+    Heading,
+    Comment,
+    Title,
+    Subtitle,
+    Credits,
+}
+
+impl Style {
+    fn font(self) -> Font {
+        match self {
+            Self::Chord | Self::Heading | Self::Title => Font::Bold,
+            _ => Font::Regular,
+        }
+    }
+
+    /// Size of the text, as a multiple of the font size
+    fn scale(self) -> f32 {
+        match self {
+            Self::Title => 1.8,
+            Self::Subtitle => 1.2,
+            Self::Credits => 0.9,
+            _ => 1.0,
+        }
+    }
+
+    fn color(self) -> Color {
+        let (r, g, b) = match self {
+            Self::Chord => (0.545, 0.102, 0.102),
+            Self::Comment | Self::Credits => (0.35, 0.35, 0.35),
+            _ => (0.0, 0.0, 0.0),
+        };
+        Color::Rgb(Rgb::new(r, g, b, None))
+    }
+}
+
+/// Measures text, so that the layout can be worked out before anything is printed
+trait Measure {
+    /// Width of `text` at a size of one point, in points
+    fn width(&self, text: &str, font: Font) -> f32;
+}
+
+/// Measures text with the advance widths of the glyphs of the embedded fonts
+struct Metrics<'a> {
+    regular: Face<'a>,
+    bold: Face<'a>,
+}
+
+impl<'a> Metrics<'a> {
+    fn new(fonts: &'a Fonts) -> Result<Self, String> {
+        let face = |data| Face::parse(data, 0).map_err(|err| err.to_string());
+        Ok(Self {
+            regular: face(&fonts.regular)?,
+            bold: face(&fonts.bold)?,
+        })
+    }
+}
+
+impl Metrics<'_> {
+    fn face(&self, font: Font) -> &Face<'_> {
+        match font {
+            Font::Regular => &self.regular,
+            Font::Bold => &self.bold,
+        }
+    }
+
+    /// Fails on the first character of `text` that the font has no glyph for,
+    /// which would be printed as a box
+    fn check(&self, text: &str, font: Font) -> Result<(), String> {
+        let face = self.face(font);
+        match text
+            .chars()
+            .find(|c| !c.is_whitespace() && face.glyph_index(*c).is_none())
+        {
+            Some(c) => Err(format!(
+                "the {} font has no glyph for '{c}' (U+{:04X})",
+                font.name(),
+                u32::from(c)
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Measure for Metrics<'_> {
+    fn width(&self, text: &str, font: Font) -> f32 {
+        let face = self.face(font);
+        // Characters missing from the font are measured as its first glyph,
+        // until `Metrics::check` rejects them
+        let units: u32 = text
+            .chars()
+            .map(|c| face.glyph_index(c).unwrap_or(GlyphId(0)))
+            .map(|glyph| u32::from(face.glyph_hor_advance(glyph).unwrap_or(0)))
+            .sum();
+        units as f32 / f32::from(face.units_per_em())
+    }
+}
+
+/// A piece of text, at a distance from the left of its column in points
+#[derive(Debug, PartialEq, Clone)]
+struct Run {
+    x: f32,
+    text: String,
+    style: Style,
+}
+
+/// A row of text, with its height in points
+#[derive(Debug, PartialEq, Clone)]
+struct Row {
+    runs: Vec<Run>,
+    height: f32,
+}
+
+/// Rows that are always printed in the same column, e.g. a line of chords and its lyrics
+#[derive(Debug, PartialEq, Clone, Default)]
+struct Block {
+    rows: Vec<Row>,
+    /// Space above the block in points, unless it starts a column
+    space_before: f32,
+}
+
+impl Block {
+    fn height(&self) -> f32 {
+        self.rows.iter().map(|row| row.height).sum()
+    }
+}
+
+/// Lays out songs in a column of a given width
+struct Layout<'a> {
+    measure: &'a dyn Measure,
+    font_size: f32,
+    /// Width of a column in points
+    width: f32,
+}
+
+impl Layout<'_> {
+    fn size(&self, style: Style) -> f32 {
+        self.font_size * style.scale()
+    }
+
+    fn text_width(&self, text: &str, style: Style) -> f32 {
+        self.measure.width(text, style.font()) * self.size(style)
+    }
+
+    fn row(&self, runs: Vec<Run>, style: Style) -> Row {
+        Row {
+            runs,
+            height: self.size(style) * LINE_HEIGHT,
+        }
+    }
+
+    fn text_row(&self, x: f32, text: &str, style: Style) -> Row {
+        let run = Run {
+            x,
+            text: text.to_string(),
+            style,
+        };
+        self.row(vec![run], style)
+    }
+
+    fn song(&self, song: Song, options: &Options) -> Vec<Block> {
+        let options = options.for_song(&song.metadata);
+        let mut blocks: Vec<Block> = self.title_block(&song.metadata).into_iter().collect();
+        for section in song.sections {
+            blocks.extend(self.section(section, &options));
+        }
+        blocks
+    }
+
+    /// The title, subtitles and credits of a song, centred in the column
+    fn title_block(&self, metadata: &Metadata) -> Option<Block> {
+        let lines = metadata
+            .title
+            .iter()
+            .map(|title| (title.clone(), Style::Title))
+            .chain(
+                metadata
+                    .subtitles
+                    .iter()
+                    .map(|s| (s.clone(), Style::Subtitle)),
+            )
+            .chain(
+                printing::fmt_credits(metadata)
+                    .into_iter()
+                    .map(|credit| (credit, Style::Credits)),
+            );
+        let rows: Vec<Row> = lines
+            .map(|(text, style)| {
+                let x = (self.width - self.text_width(&text, style)).max(0.0) / 2.0;
+                self.text_row(x, &text, style)
+            })
+            .collect();
+        if rows.is_empty() {
+            return None;
+        }
+        Some(Block {
+            rows,
+            space_before: 0.0,
+        })
+    }
+
+    /// Lays out a section, keeping its heading with its first line
+    fn section(&self, section: Section, options: &Options) -> Vec<Block> {
+        let indent = if section.kind == SectionKind::Chorus {
+            self.font_size * CHORUS_INDENT
+        } else {
+            0.0
+        };
+        let mut blocks = if section.recall && options.chorus_recall == ChorusRecall::Reference {
+            Vec::new()
+        } else {
+            match section.kind {
+                SectionKind::Tab | SectionKind::Grid => self.verbatim(&section.lines, indent),
+                _ => self.lines(&section.lines, options, indent),
+            }
+        };
+        if section.kind != SectionKind::Body {
+            let heading = self.text_row(0.0, &section.heading(), Style::Heading);
+            match blocks.first_mut() {
+                Some(first) => first.rows.insert(0, heading),
+                None => blocks.push(Block {
+                    rows: vec![heading],
+                    space_before: 0.0,
+                }),
+            }
+        }
+        if let Some(first) = blocks.first_mut() {
+            first.space_before = self.font_size * SECTION_SPACING;
+        }
+        blocks
+    }
+
+    /// Lays out lines exactly as they were written, with every character in a cell of the
+    /// same width, so that tabs and grids stay aligned
+    fn verbatim(&self, lines: &Lines, indent: f32) -> Vec<Block> {
+        let cell = self.text_width("M", Style::Lyrics);
+        printing::fmt_verbatim(lines)
+            .lines()
+            .map(|text| {
+                let runs = text
+                    .chars()
+                    .enumerate()
+                    .filter(|(_, c)| !c.is_whitespace())
+                    .map(|(i, c)| Run {
+                        x: indent + i as f32 * cell,
+                        text: c.to_string(),
+                        style: Style::Lyrics,
+                    })
+                    .collect();
+                Block {
+                    rows: vec![self.row(runs, Style::Lyrics)],
+                    space_before: 0.0,
+                }
+            })
+            .collect()
+    }
+
+    /// Lays out lyrics with their chords, one block per line
+    fn lines(&self, lines: &Lines, options: &Options, indent: f32) -> Vec<Block> {
+        let mut blocks = Vec::new();
+        for line in printing::section_lines(lines, &options.chord_style) {
+            match line {
+                SectionLine::Blank => blocks.push(Block {
+                    rows: vec![Row {
+                        runs: Vec::new(),
+                        height: self.font_size * LINE_HEIGHT / 2.0,
+                    }],
+                    space_before: 0.0,
+                }),
+                SectionLine::Chords(chords) => {
+                    let row = printing::fmt_chord_row(&chords, options.chord_rows);
+                    blocks.extend(self.wrapped(&row, Style::Chord, indent));
+                }
+                SectionLine::Lyrics(phrases) => {
+                    blocks.extend(self.lyrics_and_chords(phrases, &options.chord_style, indent));
+                }
+                SectionLine::Comment(_, text) => {
+                    blocks.extend(self.wrapped(text, Style::Comment, indent));
+                }
+            }
+        }
+        blocks
+    }
+
+    /// Lays out a line of text, wrapping it between words when it is wider than the column,
+    /// with one block for each line it wraps onto
+    fn wrapped(&self, text: &str, style: Style, indent: f32) -> Vec<Block> {
+        let width = self.width - indent;
+        let mut lines = vec![String::new()];
+        for word in text.split_inclusive(char::is_whitespace) {
+            for piece in self.break_word(word, width, style) {
+                let line = lines.last_mut().expect("a line to fill");
+                let joined = format!("{line}{piece}");
+                if !line.is_empty() && self.text_width(joined.trim_end(), style) > width {
+                    // Spaces between two words are left at the end of the line
+                    lines.push(piece.trim_start().to_string());
+                } else {
+                    *line = joined;
+                }
+            }
+        }
+        lines
+            .into_iter()
+            .map(|line| Block {
+                rows: vec![self.text_row(indent, line.trim_end(), style)],
+                space_before: 0.0,
+            })
+            .collect()
+    }
+
+    /// Lays out a line of lyrics below its chords, wrapping it between words when it is
+    /// wider than the column, with one block for each line it wraps onto
+    ///
+    /// Lyrics are moved right when a chord would run into the next one,
+    /// with a hyphen in the gap when it splits a word.
+    /// A word wider than the column is broken wherever it fills the column
+    fn lyrics_and_chords(&self, phrases: &[Phrase], style: &ChordStyle, indent: f32) -> Vec<Block> {
+        // Only the first word of a phrase is under its chord, so the line can wrap after it
+        let words = phrases.iter().flat_map(|phrase| {
+            let mut words = phrase.lyrics.split_inclusive(char::is_whitespace);
+            let first = words.next().unwrap_or_default();
+            let chord = phrase.chord.as_ref().map(|chord| chord.fmt_style(style));
+            std::iter::once((chord, first)).chain(words.map(|word| (None, word)))
+        });
+        // The line can also wrap inside a word that was broken
+        let words = words.flat_map(|(mut chord, word)| {
+            self.break_word(word, self.width - indent, Style::Lyrics)
+                .into_iter()
+                .enumerate()
+                .map(move |(i, piece)| (chord.take(), piece, i > 0))
+        });
+        let has_chords = phrases.iter().any(|p| p.chord.is_some());
+
+        let mut lines = vec![(Vec::new(), Vec::new())];
+        let mut x = indent;
+        // Where the next chord can start without running into the previous one
+        let mut chord_end = indent;
+        let mut previous = "";
+        for (chord, lyrics, broken) in words {
+            let chord_width = chord
+                .as_ref()
+                .map_or(0.0, |chord| self.text_width(chord, Style::Chord));
+            let start = if chord.is_some() { x.max(chord_end) } else { x };
+            let end = start
+                + self
+                    .text_width(lyrics.trim_end(), Style::Lyrics)
+                    .max(chord_width);
+            if x > indent && end > self.width && (broken || previous.ends_with(char::is_whitespace))
+            {
+                lines.push((Vec::new(), Vec::new()));
+                x = indent;
+                chord_end = indent;
+            }
+            let (chord_runs, lyric_runs) = lines.last_mut().expect("a line to lay out");
+            if let Some(chord) = chord {
+                if x < chord_end {
+                    let inside_word = !previous.is_empty()
+                        && !previous.ends_with(char::is_whitespace)
+                        && lyrics.starts_with(|c: char| !c.is_whitespace());
+                    if inside_word {
+                        let hyphen = self.text_width("-", Style::Lyrics);
+                        lyric_runs.push(Run {
+                            x: x + (chord_end - x - hyphen) / 2.0,
+                            text: "-".to_string(),
+                            style: Style::Lyrics,
+                        });
+                    }
+                    x = chord_end;
+                }
+                chord_end = x + chord_width + self.font_size * CHORD_GAP;
+                chord_runs.push(Run {
+                    x,
+                    text: chord,
+                    style: Style::Chord,
+                });
+            }
+            if !lyrics.trim().is_empty() {
+                lyric_runs.push(Run {
+                    x,
+                    text: lyrics.to_string(),
+                    style: Style::Lyrics,
+                });
+            }
+            x += self.text_width(lyrics, Style::Lyrics);
+            previous = lyrics;
+        }
+
+        lines
+            .into_iter()
+            .map(|(chord_runs, lyric_runs)| {
+                let mut rows = Vec::new();
+                if has_chords {
+                    rows.push(self.row(chord_runs, Style::Chord));
+                }
+                rows.push(self.row(lyric_runs, Style::Lyrics));
+                Block {
+                    rows,
+                    space_before: 0.0,
+                }
+            })
+            .collect()
+    }
+
+    /// Splits a word into pieces that are each at most `width` wide, not counting the
+    /// space after it
+    fn break_word<'w>(&self, word: &'w str, width: f32, style: Style) -> Vec<&'w str> {
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        for (i, grapheme) in word.grapheme_indices(true) {
+            let end = i + grapheme.len();
+            if i > start && self.text_width(word[start..end].trim_end(), style) > width {
+                pieces.push(&word[start..i]);
+                start = i;
+            }
+        }
+        pieces.push(&word[start..]);
+        pieces
+    }
+}
+
+/// Blocks in a column, each with its distance from the top of the column in points
+type Column = Vec<(f32, Block)>;
+
+/// Splits songs into pages of columns, starting each song on a new page
+///
+/// A block that does not fit in what is left of a column is moved to the next one,
+/// and one taller than a whole column is split between its rows
+fn paginate(songs: Vec<Vec<Block>>, height: f32, columns: usize) -> Vec<Vec<Column>> {
+    let mut pages = Vec::new();
+    for blocks in songs {
+        let mut page: Vec<Column> = vec![Vec::new()];
+        let mut y = 0.0;
+        for block in blocks.into_iter().flat_map(|block| split(block, height)) {
+            let mut space = if y > 0.0 { block.space_before } else { 0.0 };
+            if y > 0.0 && y + space + block.height() > height {
+                if page.len() == columns {
+                    pages.push(page);
+                    page = Vec::new();
+                }
+                page.push(Vec::new());
+                y = 0.0;
+                space = 0.0;
+            }
+            y += space;
+            let column = page.last_mut().expect("a column to fill");
+            let block_height = block.height();
+            column.push((y, block));
+            y += block_height;
+        }
+        pages.push(page);
+    }
+    pages
+}
+
+/// Splits a block into blocks that each fit in a column `height` points tall
+///
+/// A single row taller than a column is left on its own
+fn split(block: Block, height: f32) -> Vec<Block> {
+    if block.height() <= height {
+        return vec![block];
+    }
+    let mut blocks = vec![Block {
+        rows: Vec::new(),
+        space_before: block.space_before,
+    }];
+    let mut y = 0.0;
+    for row in block.rows {
+        let current = blocks.last_mut().expect("a block to fill");
+        if !current.rows.is_empty() && y + row.height > height {
+            blocks.push(Block::default());
+            y = 0.0;
+        }
+        y += row.height;
+        blocks.last_mut().expect("a block to fill").rows.push(row);
+    }
+    blocks
+}
+
+/// Lays out a song as a PDF document, with `fonts` embedded in it
+pub fn fmt_song(
+    song: Song,
+    options: &Options,
+    page_options: &PageOptions,
+    fonts: &Fonts,
+) -> Result<Vec<u8>, String> {
+    fmt_songs(vec![song], options, page_options, fonts)
+}
+
+/// Lays out songs as a single PDF document, e.g. a songbook, with each song on a new page
+pub fn fmt_songs(
+    songs: Vec<Song>,
+    options: &Options,
+    page_options: &PageOptions,
+    fonts: &Fonts,
+) -> Result<Vec<u8>, String> {
+    let (page_width, page_height) = page_options.page_size.dimensions();
+    let margin = page_options.margin;
+    let columns = page_options.columns.max(1);
+    let column_width =
+        (page_width - 2.0 * margin - f32::from(columns - 1) * COLUMN_GAP) / f32::from(columns);
+    let column_height = page_height - 2.0 * margin;
+    if column_width <= 0.0 || column_height <= 0.0 {
+        return Err(format!(
+            "margins of {margin} mm and {columns} columns leave no room on {page_width} × {page_height} mm pages"
+        ));
+    }
+
+    let metrics = Metrics::new(fonts)?;
+    let layout = Layout {
+        measure: &metrics,
+        font_size: page_options.font_size,
+        width: Pt::from(Mm(column_width)).0,
+    };
+    let title = songs
+        .first()
+        .and_then(|song| song.metadata.title.clone())
+        .unwrap_or_else(|| "Songs".to_string());
+    let songs = songs
+        .into_iter()
+        .map(|song| layout.song(song, options))
+        .collect();
+    let pages = paginate(songs, Pt::from(Mm(column_height)).0, usize::from(columns));
+    for run in pages
+        .iter()
+        .flatten()
+        .flatten()
+        .flat_map(|(_, block)| &block.rows)
+        .flat_map(|row| &row.runs)
+    {
+        metrics.check(&run.text, run.style.font())?;
+    }
+
+    let (document, first_page, first_layer) =
+        PdfDocument::new(title, Mm(page_width), Mm(page_height), "Songs");
+    let pdf_error = |err: printpdf::Error| err.to_string();
+    let regular = document
+        .add_external_font(fonts.regular.as_slice())
+        .map_err(pdf_error)?;
+    let bold = document
+        .add_external_font(fonts.bold.as_slice())
+        .map_err(pdf_error)?;
+    let font = |style: Style| -> &IndirectFontRef {
+        match style.font() {
+            Font::Regular => &regular,
+            Font::Bold => &bold,
+        }
+    };
+
+    for (i, page) in pages.into_iter().enumerate() {
+        let (page_index, layer_index) = if i == 0 {
+            (first_page, first_layer)
+        } else {
+            document.add_page(Mm(page_width), Mm(page_height), "Songs")
+        };
+        let layer = document.get_page(page_index).get_layer(layer_index);
+        for (c, column) in page.into_iter().enumerate() {
+            let left = margin + c as f32 * (column_width + COLUMN_GAP);
+            for (y, block) in column {
+                let mut top = y;
+                for row in block.rows {
+                    for run in row.runs {
+                        let size = layout.size(run.style);
+                        // The descent of the text is below its baseline, in the leading
+                        let baseline = top + row.height - size * (LINE_HEIGHT - 1.0);
+                        layer.set_fill_color(run.style.color());
+                        layer.use_text(
+                            run.text,
+                            size,
+                            Mm(left) + Mm::from(Pt(run.x)),
+                            Mm(page_height - margin) - Mm::from(Pt(baseline)),
+                            font(run.style),
+                        );
+                    }
+                    top += row.height;
+                }
+            }
+        }
+    }
+    document.save_to_bytes().map_err(pdf_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Line;
+
+    /// Measures every character as half a point wide, at a size of one point
+    struct HalfPoint;
+
+    impl Measure for HalfPoint {
+        fn width(&self, text: &str, _: Font) -> f32 {
+            text.chars().count() as f32 * 0.5
+        }
+    }
+
+    fn layout(width: f32) -> Layout<'static> {
+        Layout {
+            measure: &HalfPoint,
+            font_size: 10.0,
+            width,
+        }
+    }
+
+    fn phrases(line: &str) -> Vec<Phrase> {
+        let lines = crate::parser::parse(line.to_string()).unwrap();
+        match lines.lines.values().next() {
+            Some(Line::Lyrics(phrases)) => phrases.clone(),
+            _ => panic!("expected a line of lyrics"),
+        }
+    }
+
+    fn rows(blocks: Vec<Block>) -> Vec<Row> {
+        blocks.into_iter().flat_map(|block| block.rows).collect()
+    }
+
+    fn runs(row: &Row) -> Vec<(f32, &str)> {
+        row.runs
+            .iter()
+            .map(|run| (run.x, run.text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_page_size_from_str() {
+        assert_eq!("A4".parse(), Ok(PageSize::A4));
+        assert_eq!("letter".parse(), Ok(PageSize::Letter));
+        assert_eq!(
+            "148x210".parse(),
+            Ok(PageSize::Custom {
+                width: 148.0,
+                height: 210.0
+            })
+        );
+        assert!("148".parse::<PageSize>().is_err());
+        assert!("0x210".parse::<PageSize>().is_err());
+    }
+
+    #[test]
+    fn test_chords_above_lyrics() {
+        let rows = rows(layout(500.0).lyrics_and_chords(
+            &phrases("Swing [D]low, sweet"),
+            &ChordStyle::STANDARD,
+            0.0,
+        ));
+        assert_eq!(rows.len(), 2);
+        assert_eq!(runs(&rows[0]), vec![(30.0, "D")]);
+        assert_eq!(
+            runs(&rows[1]),
+            vec![(0.0, "Swing "), (30.0, "low, "), (55.0, "sweet")]
+        );
+    }
+
+    #[test]
+    fn test_chords_never_touch() {
+        // "Ebm9" and the gap after it are 25 points wide, so "riot" moves from 15 to 25 points,
+        // with a hyphen in the gap
+        let rows = rows(layout(500.0).lyrics_and_chords(
+            &phrases("[Ebm9]cha[D]riot"),
+            &ChordStyle::STANDARD,
+            0.0,
+        ));
+        assert_eq!(runs(&rows[0]), vec![(0.0, "Ebm9"), (25.0, "D")]);
+        assert_eq!(
+            runs(&rows[1]),
+            vec![(0.0, "cha"), (17.5, "-"), (25.0, "riot")]
+        );
+    }
+
+    #[test]
+    fn test_wrap_between_words() {
+        let blocks = layout(60.0).lyrics_and_chords(
+            &phrases("[C]one two [G]three"),
+            &ChordStyle::STANDARD,
+            0.0,
+        );
+        // Each line it wraps onto keeps its chords in the same column
+        assert_eq!(blocks.len(), 2);
+        let rows = rows(blocks);
+        assert_eq!(rows.len(), 4);
+        assert_eq!(runs(&rows[0]), vec![(0.0, "C")]);
+        assert_eq!(runs(&rows[1]), vec![(0.0, "one "), (20.0, "two ")]);
+        assert_eq!(runs(&rows[2]), vec![(0.0, "G")]);
+        assert_eq!(runs(&rows[3]), vec![(0.0, "three")]);
+    }
+
+    #[test]
+    fn test_break_words_wider_than_the_column() {
+        // Four characters fill the 20 points of the column
+        let rows = rows(layout(20.0).lyrics_and_chords(
+            &phrases("[C]abcdefghij [G]kl"),
+            &ChordStyle::STANDARD,
+            0.0,
+        ));
+        let lines: Vec<Vec<(f32, &str)>> = rows.iter().map(runs).collect();
+        assert_eq!(
+            lines,
+            vec![
+                vec![(0.0, "C")],
+                vec![(0.0, "abcd")],
+                vec![],
+                vec![(0.0, "efgh")],
+                vec![],
+                vec![(0.0, "ij ")],
+                vec![(0.0, "G")],
+                vec![(0.0, "kl")],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wrap_comments_and_chord_rows() {
+        // Eight characters fill the 40 points of the column
+        let song = Song::parse(
+            "{comment: slowly and softly}\n[C] [G] [Am] [F] [Dm]\n{c: pianissimo}".to_string(),
+        )
+        .unwrap();
+        let blocks = layout(40.0).song(song, &Options::default());
+        let lines: Vec<Vec<(f32, &str)>> = blocks
+            .iter()
+            .flat_map(|block| &block.rows)
+            .map(runs)
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                vec![(0.0, "slowly")],
+                vec![(0.0, "and")],
+                vec![(0.0, "softly")],
+                vec![(0.0, "C G Am F")],
+                vec![(0.0, "Dm")],
+                vec![(0.0, "pianissi")],
+                vec![(0.0, "mo")],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_heading_kept_with_first_line() {
+        let song = Song::parse("{soc}\n[C]Hi\n[G]there\n{eoc}".to_string()).unwrap();
+        let blocks = layout(500.0).song(song, &Options::default());
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(runs(&blocks[0].rows[0]), vec![(0.0, "Chorus")]);
+        assert_eq!(runs(&blocks[0].rows[1]), vec![(15.0, "C")]);
+        assert_eq!(runs(&blocks[0].rows[2]), vec![(15.0, "Hi")]);
+        assert_eq!(blocks[0].space_before, 8.0);
+        assert_eq!(blocks[1].space_before, 0.0);
+    }
+
+    #[test]
+    fn test_paginate() {
+        let block = |height| Block {
+            rows: vec![Row {
+                runs: Vec::new(),
+                height,
+            }],
+            space_before: 5.0,
+        };
+        let songs = vec![
+            vec![block(40.0), block(40.0), block(40.0), block(90.0)],
+            vec![block(10.0)],
+        ];
+        let pages = paginate(songs, 100.0, 2);
+        let tops: Vec<Vec<Vec<f32>>> = pages
+            .iter()
+            .map(|page| {
+                page.iter()
+                    .map(|column| column.iter().map(|(y, _)| *y).collect())
+                    .collect()
+            })
+            .collect();
+        // Blocks never straddle two columns, and the space above a block is dropped at
+        // the top of a column
+        assert_eq!(
+            tops,
+            vec![
+                vec![vec![0.0, 45.0], vec![0.0]],
+                vec![vec![0.0]],
+                vec![vec![0.0]]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_paginate_splits_blocks_taller_than_a_column() {
+        let row = Row {
+            runs: Vec::new(),
+            height: 40.0,
+        };
+        let block = Block {
+            rows: vec![row.clone(), row.clone(), row],
+            space_before: 5.0,
+        };
+        let pages = paginate(vec![vec![block]], 100.0, 2);
+        let heights: Vec<Vec<f32>> = pages[0]
+            .iter()
+            .map(|column| column.iter().map(|(_, block)| block.height()).collect())
+            .collect();
+        assert_eq!(heights, vec![vec![80.0], vec![40.0]]);
+    }
+
+    #[test]
+    fn test_fmt_songs() {
+        let fonts = Fonts::bundled();
+        let song =
+            Song::parse("{title: Hi}\n{sov}\n[C]Hello [G]Привет, Γειά\n{eov}".to_string()).unwrap();
+        let page_options = PageOptions {
+            page_size: PageSize::Letter,
+            columns: 2,
+            ..Default::default()
+        };
+        let pdf = fmt_songs(
+            vec![song.clone(), song],
+            &Options::default(),
+            &page_options,
+            &fonts,
+        )
+        .unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+    }
+
+    #[test]
+    fn test_missing_glyphs() {
+        let song = Song::parse("[C]Hello [G]파이팅".to_string()).unwrap();
+        let pdf = fmt_songs(
+            vec![song],
+            &Options::default(),
+            &PageOptions::default(),
+            &Fonts::bundled(),
+        );
+        assert_eq!(
+            pdf.unwrap_err(),
+            "the regular font has no glyph for '파' (U+D30C)"
+        );
+    }
+
+    #[test]
+    fn test_fonts_must_be_readable() {
+        assert_eq!(
+            Fonts::new(Vec::new(), Vec::new()).unwrap_err(),
+            "cannot read the regular font: unknown magic"
+        );
+    }
+
+    #[test]
+    fn test_no_room_on_page() {
+        let fonts = Fonts {
+            regular: Vec::new(),
+            bold: Vec::new(),
+        };
+        let page_options = PageOptions {
+            margin: 150.0,
+            ..Default::default()
+        };
+        assert!(fmt_songs(Vec::new(), &Options::default(), &page_options, &fonts).is_err());
+    }
+}
@@ -1,12 +1,23 @@
+//! Pitch-related functionality
+
 use crate::{HALF_STEP, WHOLE_STEP};
 use std::fmt;
-
-/// Pitch-related functionality
+use std::str::FromStr;
 
 /// Pitch Space is the set of all pitches: A, B, C, D, E, F, G
 /// `caramell` follows the Western 12-tone system, with 7 distinct pitch classes
 const PITCH_SPACE_SIZE: usize = 7;
 
+/// Number of semitones in an octave
+const OCTAVE_SIZE: i32 = 12;
+
+/// Positions of the simplest spellings on the line of fifths, from Gb to A#
+/// Every pitch has exactly one spelling in this range, except for the 5 black keys
+const SIMPLE_SPELLING: std::ops::RangeInclusive<i32> = -5..=11;
+
+/// Line-of-fifths position of C, the tonic of the major key without accidentals
+const C_POSITION: i32 = 1;
+
 const PITCH_POSITION_OFFSET: usize = 14;
 const LINE_OF_FIFTHS: &[&str] = &[
     "Fbb", "Cbb", "Gbb", "Dbb", "Abb", "Ebb", "Bbb", "Fb", "Cb", "Gb", "Db", "Ab", "Eb", "Bb", "F",
@@ -44,6 +55,16 @@ pub enum Accidental {
     DoubleFlat = -WHOLE_STEP,
 }
 
+/// How accidentals are written
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub enum Notation {
+    /// As typed on a keyboard, e.g. `F#`, `Bb` or `C##`
+    #[default]
+    Ascii,
+    /// With the musical symbols, e.g. `F♯`, `B♭` or `C𝄪`
+    Typographic,
+}
+
 impl PitchClass {
     pub fn new(s: &str) -> Result<Self, String> {
         match s {
@@ -83,23 +104,39 @@ impl fmt::Display for PitchClass {
 impl Accidental {
     pub fn new(s: &str) -> Result<Self, String> {
         match s {
-            "##" => Ok(Self::DoubleSharp),
-            "#" => Ok(Self::Sharp),
+            "##" | "x" | "𝄪" => Ok(Self::DoubleSharp),
+            "#" | "♯" => Ok(Self::Sharp),
             "♮" => Ok(Self::Natural),
-            "b" => Ok(Self::Flat),
-            "bb" => Ok(Self::DoubleFlat),
+            "b" | "♭" => Ok(Self::Flat),
+            "bb" | "𝄫" => Ok(Self::DoubleFlat),
             _ => Err("unknown accidental".to_string()),
         }
     }
+
+    /// Symbol of the accidental, e.g. `#` or `♯`
+    /// There is no ASCII natural sign, so `♮` is used in both notations
+    pub fn symbol(&self, notation: Notation) -> &'static str {
+        match (self, notation) {
+            (Self::DoubleSharp, Notation::Ascii) => "##",
+            (Self::DoubleSharp, Notation::Typographic) => "𝄪",
+            (Self::Sharp, Notation::Ascii) => "#",
+            (Self::Sharp, Notation::Typographic) => "♯",
+            (Self::Natural, _) => "♮",
+            (Self::Flat, Notation::Ascii) => "b",
+            (Self::Flat, Notation::Typographic) => "♭",
+            (Self::DoubleFlat, Notation::Ascii) => "bb",
+            (Self::DoubleFlat, Notation::Typographic) => "𝄫",
+        }
+    }
 }
 
 impl fmt::Display for Accidental {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", self)
+        write!(f, "{}", self.symbol(Notation::Ascii))
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Copy, Clone)]
 pub struct Note {
     pub pitch: PitchClass,
     pub accidental: Option<Accidental>,
@@ -107,15 +144,23 @@ pub struct Note {
 
 impl Note {
     pub fn new(s: &str) -> Result<Self, String> {
-        let pitch = PitchClass::new(&s[0..1])?;
-        let accidental = if s.len() > 1 {
-            Some(Accidental::new(&s[1..])?)
+        // The first character may take up several bytes, e.g. in `♯`
+        let letter = s.chars().next().map_or(0, char::len_utf8);
+        let pitch = PitchClass::new(&s[..letter])?;
+        let accidental = if s.len() > letter {
+            Some(Accidental::new(&s[letter..])?)
         } else {
             None
         };
         Ok(Self { pitch, accidental })
     }
 
+    /// Writes the note with its accidental in the given notation, e.g. `F♯`
+    pub fn fmt_notation(&self, notation: Notation) -> String {
+        let accidental = self.accidental.map_or("", |a| a.symbol(notation));
+        format!("{}{accidental}", self.pitch)
+    }
+
     /// Determines position of tonal pitch class along the line of fifths
     /// The "line of fifths" is a representation of pitch space where there are no enharmonic equivalents;
     /// Instead, pitches classes like F# and Gb are treated as distinct
@@ -127,18 +172,399 @@ impl Note {
         PITCH_SPACE_SIZE as i32 * accidental + self.pitch as i32
     }
 
+    /// Pitch class as the number of half steps above C, from 0 to 11
+    pub fn semitones(&self) -> i32 {
+        // Every step along the line of fifths is a P5, i.e. 7 half steps
+        ((self.position() - C_POSITION) * 7).rem_euclid(OCTAVE_SIZE)
+    }
+
+    /// Inverse of [`Note::position`]
+    /// Fails for positions that would need more than a double sharp or double flat
+    pub fn from_position(position: i32) -> Result<Self, String> {
+        let name = usize::try_from(position + PITCH_POSITION_OFFSET as i32)
+            .ok()
+            .and_then(|i| LINE_OF_FIFTHS.get(i))
+            .ok_or(format!(
+                "no note at position '{position}' on the line of fifths"
+            ))?;
+        Self::new(name)
+    }
+
+    /// Transposes by a signed number of half steps, picking a sensible spelling
+    ///
+    /// The note moves along the line of fifths by at most 6 fifths, which corresponds to
+    /// the simplest interval spanning `half_steps` (e.g. +1 is a minor 2nd, so C becomes Db).
+    /// Results needing double accidentals or spelled Cb, Fb, E# or B# are respelled
+    /// with the enharmonic equivalent between Gb and A#
     pub fn transpose(&self, half_steps: i32) -> Note {
-        todo!("transpose method in progress");
+        // A P5 spans 7 half steps, and 7 * 7 = 49 = 1 (mod 12), so 7 is its own inverse
+        let fifths = (half_steps * 7 + 5).rem_euclid(OCTAVE_SIZE) - 5;
+        let mut position = self.position() + fifths;
+        if !SIMPLE_SPELLING.contains(&position) {
+            let lowest = *SIMPLE_SPELLING.start();
+            position = (position - lowest).rem_euclid(OCTAVE_SIZE) + lowest;
+        }
+        Self::from_position(position).expect("simple spellings are on the line of fifths")
+    }
+
+    /// Adds an interval on top of this note, e.g. C plus a major 3rd is E, never Fb
+    pub fn add_interval(&self, interval: &Interval) -> Result<Note, String> {
+        Self::from_position(self.position() + interval.fifths())
     }
 }
 
 impl fmt::Display for Note {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let accidental = match &self.accidental {
-            Some(a) => a.to_string(),
-            None => "".to_string(),
+        write!(f, "{}", self.fmt_notation(Notation::Ascii))
+    }
+}
+
+/// Line-of-fifths distance of the perfect or major simple intervals, from unison to 7th
+/// For example, a major 3rd (C to E) spans 4 perfect 5ths: C-G-D-A-E
+const INTERVAL_FIFTHS: [i32; PITCH_SPACE_SIZE] = [0, 2, 4, -1, 1, 3, 5];
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum IntervalQuality {
+    Diminished,
+    Minor,
+    Perfect,
+    Major,
+    Augmented,
+}
+
+impl IntervalQuality {
+    pub fn new(s: &str) -> Result<Self, String> {
+        match s {
+            "d" => Ok(Self::Diminished),
+            "m" => Ok(Self::Minor),
+            "P" => Ok(Self::Perfect),
+            "M" => Ok(Self::Major),
+            "A" => Ok(Self::Augmented),
+            _ => Err("unknown interval quality".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for IntervalQuality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            Self::Diminished => "d",
+            Self::Minor => "m",
+            Self::Perfect => "P",
+            Self::Major => "M",
+            Self::Augmented => "A",
         };
-        write!(f, "{}{}", &self.pitch.to_string(), accidental)
+        write!(f, "{symbol}")
+    }
+}
+
+/// An interval between two notes, e.g. a minor 3rd (`m3`) or a major 9th (`M9`)
+/// Numbers above 8 are compound intervals, spanning one or more octaves
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Interval {
+    quality: IntervalQuality,
+    number: u8,
+}
+
+impl Interval {
+    /// Parses an interval such as `m3`, `P5`, `A4`, `d7` or `M9`
+    pub fn new(s: &str) -> Result<Self, String> {
+        if s.is_empty() || !s.is_char_boundary(1) {
+            return Err("unknown interval quality".to_string());
+        }
+        let quality = IntervalQuality::new(&s[..1])?;
+        let number = s[1..]
+            .parse::<u8>()
+            .map_err(|_| format!("unknown interval number '{}'", &s[1..]))?;
+        Self::from_quality(quality, number)
+    }
+
+    /// The interval of the given quality and number, e.g. a minor 3rd
+    /// Fails for a number of 0, or a quality the number cannot have, e.g. a perfect 3rd
+    pub fn from_quality(quality: IntervalQuality, number: u8) -> Result<Self, String> {
+        if number == 0 {
+            return Err("unknown interval number '0'".to_string());
+        }
+        let interval = Self { quality, number };
+        interval.offset()?;
+        Ok(interval)
+    }
+
+    pub fn quality(&self) -> IntervalQuality {
+        self.quality
+    }
+
+    /// Number of letter names spanned, counting both ends, e.g. 3 for a 3rd
+    pub fn number(&self) -> u8 {
+        self.number
+    }
+
+    /// Ascending simple interval from `from` up to `to`
+    pub fn between(from: &Note, to: &Note) -> Result<Self, String> {
+        let fifths = to.position() - from.position();
+        let simple = (fifths * 4).rem_euclid(PITCH_SPACE_SIZE as i32) as usize;
+        let offset = fifths - INTERVAL_FIFTHS[simple];
+        let perfect = Self::is_perfect(simple);
+        let quality = match (offset / PITCH_SPACE_SIZE as i32, perfect) {
+            (-1, true) | (-2, false) => IntervalQuality::Diminished,
+            (-1, false) => IntervalQuality::Minor,
+            (0, true) => IntervalQuality::Perfect,
+            (0, false) => IntervalQuality::Major,
+            (1, _) => IntervalQuality::Augmented,
+            _ => return Err(format!("interval from {from} to {to} is beyond augmented")),
+        };
+        Ok(Self {
+            quality,
+            number: simple as u8 + 1,
+        })
+    }
+
+    /// Simple interval number counted from 0, i.e. 0 for a unison and 6 for a 7th
+    fn simple(&self) -> usize {
+        (self.number as usize - 1) % PITCH_SPACE_SIZE
+    }
+
+    fn octaves(&self) -> i32 {
+        (self.number as i32 - 1) / PITCH_SPACE_SIZE as i32
+    }
+
+    /// Unisons, 4ths, 5ths and their compounds are perfect intervals, the rest are major or minor
+    fn is_perfect(simple: usize) -> bool {
+        matches!(simple, 0 | 3 | 4)
+    }
+
+    /// Line-of-fifths distance from the perfect or major interval of the same number
+    fn offset(&self) -> Result<i32, String> {
+        let perfect = Self::is_perfect(self.simple());
+        let steps = match (self.quality, perfect) {
+            (IntervalQuality::Diminished, true) => -1,
+            (IntervalQuality::Diminished, false) => -2,
+            (IntervalQuality::Minor, false) => -1,
+            (IntervalQuality::Perfect, true) | (IntervalQuality::Major, false) => 0,
+            (IntervalQuality::Augmented, _) => 1,
+            _ => return Err(format!("there is no interval {self}")),
+        };
+        Ok(steps * PITCH_SPACE_SIZE as i32)
+    }
+
+    /// Line-of-fifths distance spanned by the interval, ignoring octaves
+    pub fn fifths(&self) -> i32 {
+        let offset = self
+            .offset()
+            .expect("intervals are validated when they are made");
+        INTERVAL_FIFTHS[self.simple()] + offset
+    }
+
+    /// Size of the interval in half steps, including octaves
+    pub fn semitones(&self) -> i32 {
+        let fifths = self.fifths();
+        // Each fifth climbs 4 letter names; the whole octaves among them are dropped
+        let octaves = (fifths * 4 - self.simple() as i32) / PITCH_SPACE_SIZE as i32;
+        fifths * 7 - OCTAVE_SIZE * octaves + OCTAVE_SIZE * self.octaves()
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.quality, self.number)
+    }
+}
+
+/// Modes of the major scale, each starting on a different degree of it,
+/// e.g. D dorian has the notes of C major
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub enum Mode {
+    #[default]
+    Major,
+    Dorian,
+    Phrygian,
+    Lydian,
+    Mixolydian,
+    Minor,
+    Locrian,
+}
+
+impl Mode {
+    /// Parses a mode such as `m`, `minor` or `dorian`, where an empty string is major
+    pub fn new(s: &str) -> Result<Self, String> {
+        // `m` is minor, but `M` is major as in chord symbols
+        match s {
+            "" | "M" => return Ok(Self::Major),
+            "m" => return Ok(Self::Minor),
+            _ => {}
+        }
+        match s.to_lowercase().as_str() {
+            "maj" | "major" | "ionian" => Ok(Self::Major),
+            "dorian" => Ok(Self::Dorian),
+            "phrygian" => Ok(Self::Phrygian),
+            "lydian" => Ok(Self::Lydian),
+            "mixolydian" => Ok(Self::Mixolydian),
+            "min" | "minor" | "aeolian" => Ok(Self::Minor),
+            "locrian" => Ok(Self::Locrian),
+            _ => Err(format!("unknown mode '{s}'")),
+        }
+    }
+
+    /// Fifths from the tonic of the relative major up to the tonic of the mode,
+    /// e.g. 3 for minor, as A is 3 fifths above C
+    fn fifths(&self) -> i32 {
+        match self {
+            Self::Lydian => -1,
+            Self::Major => 0,
+            Self::Mixolydian => 1,
+            Self::Dorian => 2,
+            Self::Minor => 3,
+            Self::Phrygian => 4,
+            Self::Locrian => 5,
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format!("{self:?}").to_lowercase())
+    }
+}
+
+/// A key, made up of a tonic and a mode, e.g. `Bb` major or `F#` minor
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Key {
+    pub tonic: Note,
+    pub mode: Mode,
+}
+
+impl Key {
+    /// Parses a key such as `Bb`, `F#m`, `C minor` or `D dorian`
+    pub fn new(s: &str) -> Result<Self, String> {
+        // The longest prefix that is a note, so that e.g. `Bbm` is B flat minor
+        let (tonic, mode) = s
+            .char_indices()
+            .map(|(i, c)| i + c.len_utf8())
+            .rev()
+            .find_map(|end| Note::new(&s[..end]).ok().map(|tonic| (tonic, &s[end..])))
+            .ok_or_else(|| format!("unknown key '{s}'"))?;
+        let mode = Mode::new(mode.trim()).map_err(|_| format!("unknown key '{s}'"))?;
+        Ok(Self { tonic, mode })
+    }
+
+    /// Number of sharps (positive) or flats (negative) in the key signature
+    pub fn signature(&self) -> i32 {
+        self.tonic.position() - self.mode.fifths() - C_POSITION
+    }
+
+    /// Whether notes outside of the key are better spelled with flats than sharps
+    pub fn prefers_flats(&self) -> bool {
+        self.signature() < 0
+    }
+
+    /// The sharps or flats of the key signature in the order they are written,
+    /// e.g. F# and C# for D major, or Bb, Eb and Ab for C minor
+    /// Fails for keys that would need more than double sharps or double flats
+    pub fn accidentals(&self) -> Result<Vec<Note>, String> {
+        let signature = self.signature();
+        // Sharps are added upwards from F#, and flats downwards from Bb
+        let first_sharp = C_POSITION + 6;
+        let positions: Vec<i32> = if signature >= 0 {
+            (first_sharp..first_sharp + signature).collect()
+        } else {
+            (signature..C_POSITION - 1).rev().collect()
+        };
+        positions.into_iter().map(Note::from_position).collect()
+    }
+
+    /// Notes of the scale from the tonic upwards, e.g. D, E, F, G, A, B and C for D dorian
+    /// Fails for keys that would need more than double sharps or double flats
+    pub fn scale(&self) -> Result<Vec<Note>, String> {
+        // The 7 notes of a key are consecutive on the line of fifths,
+        // starting one fifth below the tonic of its relative major
+        let lowest = self.signature() + C_POSITION - 1;
+        (0..PITCH_SPACE_SIZE as i32)
+            .map(|degree| {
+                // Every step up the scale is 2 fifths up, and 7 positions share a letter
+                let letter = (self.tonic.pitch as i32 + 2 * degree).rem_euclid(7);
+                let position = (lowest..lowest + 7)
+                    .find(|position| position.rem_euclid(7) == letter)
+                    .expect("every letter is in 7 consecutive fifths");
+                Note::from_position(position)
+            })
+            .collect()
+    }
+
+    /// Scale degree of `note` in the key, from 1 for the tonic to 7,
+    /// or `None` if the note is not in the scale as spelled
+    pub fn degree(&self, note: &Note) -> Option<usize> {
+        let scale = self.scale().ok()?;
+        let position = scale.iter().position(|n| n.position() == note.position())?;
+        Some(position + 1)
+    }
+
+    /// Moves the key by `half_steps`, spelling the tonic with the fewest accidentals in the signature
+    /// A key that does not move keeps its spelling, e.g. F# major stays F# major
+    pub fn transpose(&self, half_steps: i32) -> Self {
+        if half_steps.rem_euclid(OCTAVE_SIZE) == 0 {
+            return *self;
+        }
+        Self {
+            tonic: self.tonic.transpose(half_steps),
+            mode: self.mode,
+        }
+        .simplest()
+    }
+
+    /// The same key spelled with the fewest accidentals in the signature, e.g. Ab for G#
+    /// Ties between 6 sharps and 6 flats go to the flat key
+    pub fn simplest(&self) -> Self {
+        let candidates = [
+            self.tonic.position() - OCTAVE_SIZE,
+            self.tonic.position(),
+            self.tonic.position() + OCTAVE_SIZE,
+        ];
+        candidates
+            .into_iter()
+            .filter_map(|position| Note::from_position(position).ok())
+            .map(|tonic| Self {
+                tonic,
+                mode: self.mode,
+            })
+            .min_by_key(|key| (key.signature().abs(), key.signature()))
+            .unwrap_or(*self)
+    }
+
+    /// Respells `note` so every pitch class has exactly one spelling in this key
+    ///
+    /// Notes are taken from a window of 12 consecutive positions on the line of fifths
+    /// that contains the scale, so borrowed chords are spelled as flats on the lowered
+    /// degrees (bIII, bVI, bVII) and sharps on the raised 4th.
+    /// For keys whose scale fits in Gb..A#, the window never leaves that range,
+    /// so there are no double accidentals, Cb, Fb, E# or B#.
+    /// Keys with 6 or 7 sharps or flats keep their own scale, e.g. E# in F# major
+    pub fn spell(&self, note: &Note) -> Note {
+        let scale = self.signature() + C_POSITION - 1;
+        let mut lowest = scale - 4;
+        let simple = *SIMPLE_SPELLING.start()..=*SIMPLE_SPELLING.end() - 11;
+        if SIMPLE_SPELLING.contains(&scale) && SIMPLE_SPELLING.contains(&(scale + 6)) {
+            lowest = lowest.clamp(*simple.start(), *simple.end());
+        }
+        let position = (note.position() - lowest).rem_euclid(OCTAVE_SIZE) + lowest;
+        Note::from_position(position).expect("the spelling window is on the line of fifths")
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.mode {
+            Mode::Major => write!(f, "{}", self.tonic),
+            Mode::Minor => write!(f, "{}m", self.tonic),
+            mode => write!(f, "{} {mode}", self.tonic),
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
     }
 }
 
@@ -155,6 +581,9 @@ mod tests {
     #[test]
     fn parse_accidental() {
         assert_eq!(Accidental::new("♮"), Ok(Accidental::Natural));
+        assert_eq!(Accidental::new("x"), Ok(Accidental::DoubleSharp));
+        assert_eq!(Accidental::new("♯"), Ok(Accidental::Sharp));
+        assert_eq!(Accidental::new("𝄫"), Ok(Accidental::DoubleFlat));
         assert_eq!(Accidental::new("&"), Err("unknown accidental".to_string()));
     }
 
@@ -176,6 +605,21 @@ mod tests {
         );
         assert_eq!(Note::new("$"), Err("unknown pitch".to_string()));
         assert_eq!(Note::new("E%"), Err("unknown accidental".to_string()));
+        assert_eq!(Note::new("♯"), Err("unknown pitch".to_string()));
+        assert_eq!(Note::new(""), Err("unknown pitch".to_string()));
+        assert_eq!(Note::new("B♭"), Note::new("Bb"));
+        assert_eq!(Note::new("F𝄪"), Note::new("F##"));
+    }
+
+    #[test]
+    fn display_note() {
+        for (ascii, typographic) in [("C", "C"), ("F#", "F♯"), ("Bbb", "B𝄫"), ("E♮", "E♮")]
+        {
+            let note = Note::new(ascii).unwrap();
+            assert_eq!(note.to_string(), ascii);
+            assert_eq!(note.fmt_notation(Notation::Typographic), typographic);
+            assert_eq!(Note::new(typographic), Ok(note));
+        }
     }
 
     #[test]
@@ -183,4 +627,266 @@ mod tests {
         assert_eq!(Note::new("Gbb").unwrap().position(), -12);
         assert_eq!(Note::new("A#").unwrap().position(), 11);
     }
+
+    #[test]
+    fn test_note_semitones() {
+        assert_eq!(Note::new("C").unwrap().semitones(), 0);
+        assert_eq!(Note::new("Db").unwrap().semitones(), 1);
+        assert_eq!(Note::new("C#").unwrap().semitones(), 1);
+        assert_eq!(Note::new("Cb").unwrap().semitones(), 11);
+        assert_eq!(Note::new("B#").unwrap().semitones(), 0);
+    }
+
+    #[test]
+    fn test_note_from_position() {
+        assert_eq!(Note::from_position(-12), Note::new("Gbb"));
+        assert_eq!(Note::from_position(1), Note::new("C"));
+        assert!(Note::from_position(21).is_err());
+        assert!(Note::from_position(-15).is_err());
+    }
+
+    #[test]
+    fn test_transpose() {
+        let transpose = |s, n| Note::new(s).unwrap().transpose(n).to_string();
+        assert_eq!(transpose("C", 0), "C");
+        assert_eq!(transpose("C", 1), "Db");
+        assert_eq!(transpose("C", 2), "D");
+        assert_eq!(transpose("C", 6), "F#");
+        assert_eq!(transpose("C", 10), "Bb");
+        assert_eq!(transpose("C", -1), "B");
+        assert_eq!(transpose("C", 13), "Db");
+        assert_eq!(transpose("E", 1), "F");
+        assert_eq!(transpose("F#", 1), "G");
+        assert_eq!(transpose("Ab", 1), "A");
+        assert_eq!(transpose("Db", 1), "D");
+        assert_eq!(transpose("Cb", 0), "B");
+        assert_eq!(transpose("A#", 2), "C");
+    }
+
+    #[test]
+    fn parse_interval() {
+        let minor_third = Interval::new("m3").unwrap();
+        assert_eq!(minor_third.quality(), IntervalQuality::Minor);
+        assert_eq!(minor_third.number(), 3);
+        assert_eq!(
+            Interval::from_quality(IntervalQuality::Minor, 3),
+            Ok(minor_third)
+        );
+        assert_eq!(Interval::new("M9").unwrap().to_string(), "M9");
+        assert_eq!(
+            Interval::new("P3"),
+            Err("there is no interval P3".to_string())
+        );
+        assert_eq!(
+            Interval::new("m5"),
+            Err("there is no interval m5".to_string())
+        );
+        assert!(Interval::new("M0").is_err());
+        assert!(Interval::from_quality(IntervalQuality::Perfect, 0).is_err());
+        assert_eq!(
+            Interval::from_quality(IntervalQuality::Perfect, 10),
+            Err("there is no interval P10".to_string())
+        );
+        assert!(Interval::new("X3").is_err());
+        assert!(Interval::new("").is_err());
+    }
+
+    #[test]
+    fn test_interval_semitones() {
+        let semitones = |s| Interval::new(s).unwrap().semitones();
+        assert_eq!(semitones("P1"), 0);
+        assert_eq!(semitones("m2"), 1);
+        assert_eq!(semitones("m3"), 3);
+        assert_eq!(semitones("M3"), 4);
+        assert_eq!(semitones("A4"), 6);
+        assert_eq!(semitones("d5"), 6);
+        assert_eq!(semitones("P5"), 7);
+        assert_eq!(semitones("d7"), 9);
+        assert_eq!(semitones("A7"), 12);
+        assert_eq!(semitones("P8"), 12);
+        assert_eq!(semitones("M9"), 14);
+        assert_eq!(semitones("A11"), 18);
+        assert_eq!(semitones("M13"), 21);
+    }
+
+    #[test]
+    fn test_interval_between() {
+        let between = |a, b| {
+            Interval::between(&Note::new(a).unwrap(), &Note::new(b).unwrap()).map(|i| i.to_string())
+        };
+        assert_eq!(between("C", "C"), Ok("P1".to_string()));
+        assert_eq!(between("C", "E"), Ok("M3".to_string()));
+        assert_eq!(between("C", "Eb"), Ok("m3".to_string()));
+        assert_eq!(between("C", "F#"), Ok("A4".to_string()));
+        assert_eq!(between("C", "Gb"), Ok("d5".to_string()));
+        assert_eq!(between("C#", "Bb"), Ok("d7".to_string()));
+        assert_eq!(between("E", "C"), Ok("m6".to_string()));
+        assert_eq!(between("B", "F"), Ok("d5".to_string()));
+        assert!(between("C", "F##").is_err());
+    }
+
+    #[test]
+    fn test_add_interval() {
+        let add = |n, i| {
+            Note::new(n)
+                .unwrap()
+                .add_interval(&Interval::new(i).unwrap())
+                .map(|n| n.to_string())
+        };
+        assert_eq!(add("C", "M3"), Ok("E".to_string()));
+        assert_eq!(add("Eb", "m3"), Ok("Gb".to_string()));
+        assert_eq!(add("C#", "d7"), Ok("Bb".to_string()));
+        assert_eq!(add("D", "M9"), Ok("E".to_string()));
+        assert_eq!(add("F#", "A4"), Ok("B#".to_string()));
+        assert_eq!(add("C#", "M3"), Ok("E#".to_string()));
+        assert_eq!(add("C", "d5"), Ok("Gb".to_string()));
+        assert!(add("B##", "A4").is_err());
+    }
+
+    fn key(s: &str) -> Key {
+        Key::new(s).unwrap()
+    }
+
+    fn names(notes: Vec<Note>) -> Vec<String> {
+        notes.iter().map(Note::to_string).collect()
+    }
+
+    #[test]
+    fn parse_key() {
+        assert_eq!(
+            Key::new("F#m"),
+            Ok(Key {
+                tonic: Note::new("F#").unwrap(),
+                mode: Mode::Minor
+            })
+        );
+        assert_eq!(key("Bbm").tonic, Note::new("Bb").unwrap());
+        assert_eq!(key("C minor").mode, Mode::Minor);
+        assert_eq!(key("Dmaj").mode, Mode::Major);
+        assert_eq!(key("D Dorian").mode, Mode::Dorian);
+        assert_eq!(key("E♭ lydian").tonic, Note::new("Eb").unwrap());
+        assert_eq!(Key::new("m"), Err("unknown key 'm'".to_string()));
+        assert_eq!(Key::new("H"), Err("unknown key 'H'".to_string()));
+        assert_eq!(
+            Key::new("C blues"),
+            Err("unknown key 'C blues'".to_string())
+        );
+    }
+
+    #[test]
+    fn display_key() {
+        for s in ["C", "Bb", "F#m", "D dorian", "G mixolydian"] {
+            assert_eq!(key(s).to_string(), s);
+        }
+        assert_eq!(key("A minor").to_string(), "Am");
+    }
+
+    #[test]
+    fn test_key_signature() {
+        let signature = |s: &str| key(s).signature();
+        assert_eq!(signature("C"), 0);
+        assert_eq!(signature("Am"), 0);
+        assert_eq!(signature("Bb"), -2);
+        assert_eq!(signature("F#m"), 3);
+        assert_eq!(signature("C#"), 7);
+        assert_eq!(signature("D dorian"), 0);
+        assert_eq!(signature("F lydian"), 0);
+        assert_eq!(signature("B locrian"), 0);
+        assert_eq!(signature("A mixolydian"), 2);
+        assert_eq!(signature("E phrygian"), 0);
+        assert!(key("Eb").prefers_flats());
+        assert!(!key("Em").prefers_flats());
+    }
+
+    #[test]
+    fn test_key_accidentals() {
+        assert_eq!(names(key("D").accidentals().unwrap()), vec!["F#", "C#"]);
+        assert_eq!(
+            names(key("Cm").accidentals().unwrap()),
+            vec!["Bb", "Eb", "Ab"]
+        );
+        assert!(key("Am").accidentals().unwrap().is_empty());
+        assert_eq!(
+            names(key("G#").accidentals().unwrap()),
+            vec!["F#", "C#", "G#", "D#", "A#", "E#", "B#", "F##"]
+        );
+    }
+
+    #[test]
+    fn test_key_scale() {
+        assert_eq!(
+            names(key("Bb").scale().unwrap()),
+            vec!["Bb", "C", "D", "Eb", "F", "G", "A"]
+        );
+        assert_eq!(
+            names(key("F#m").scale().unwrap()),
+            vec!["F#", "G#", "A", "B", "C#", "D", "E"]
+        );
+        assert_eq!(
+            names(key("D dorian").scale().unwrap()),
+            vec!["D", "E", "F", "G", "A", "B", "C"]
+        );
+        assert_eq!(
+            names(key("G#").scale().unwrap()),
+            vec!["G#", "A#", "B#", "C#", "D#", "E#", "F##"]
+        );
+        assert!(key("B##").scale().is_err());
+    }
+
+    #[test]
+    fn test_key_degree() {
+        let g = key("G");
+        assert_eq!(g.degree(&Note::new("G").unwrap()), Some(1));
+        assert_eq!(g.degree(&Note::new("F#").unwrap()), Some(7));
+        assert_eq!(g.degree(&Note::new("F").unwrap()), None);
+        assert_eq!(key("Am").degree(&Note::new("C").unwrap()), Some(3));
+    }
+
+    #[test]
+    fn test_key_transpose_prefers_fewer_accidentals() {
+        let transpose = |s: &str, n| key(s).transpose(n).tonic.to_string();
+        assert_eq!(transpose("C", 1), "Db");
+        assert_eq!(transpose("C", 6), "Gb");
+        assert_eq!(transpose("A", 1), "Bb");
+        assert_eq!(transpose("Am", 1), "Bb");
+        assert_eq!(transpose("Am", 4), "C#");
+        assert_eq!(transpose("E", 1), "F");
+        assert_eq!(transpose("D dorian", 1), "Eb");
+    }
+
+    #[test]
+    fn test_key_spell() {
+        let spell = |k: &str, n: &str| key(k).spell(&Note::new(n).unwrap()).to_string();
+        assert_eq!(spell("F", "A#"), "Bb");
+        assert_eq!(spell("E", "Db"), "C#");
+        assert_eq!(spell("C", "Ab"), "Ab");
+        assert_eq!(spell("C", "Gb"), "F#");
+        // The 7th degree of F# major, e.g. the 5th of C#dim
+        assert_eq!(spell("F#", "F"), "E#");
+        assert_eq!(spell("Gb", "B"), "Cb");
+        for k in ["F#", "C#", "Gb", "Cb", "D#m", "Ebm"] {
+            let scale = key(k).scale().unwrap();
+            for note in &scale {
+                assert_eq!(key(k).spell(note), *note, "{note} in {k}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_key_simplest() {
+        let simplest = |s: &str| key(s).simplest().to_string();
+        assert_eq!(simplest("G#"), "Ab");
+        assert_eq!(simplest("B#"), "C");
+        assert_eq!(simplest("Fb"), "E");
+        assert_eq!(simplest("A#m"), "Bbm");
+        assert_eq!(simplest("F#"), "Gb");
+        assert_eq!(simplest("D"), "D");
+    }
+
+    #[test]
+    fn test_key_transpose_by_nothing() {
+        assert_eq!(key("F#").transpose(0), key("F#"));
+        assert_eq!(key("Gb").transpose(0), key("Gb"));
+        assert_eq!(key("C#m").transpose(12), key("C#m"));
+    }
 }
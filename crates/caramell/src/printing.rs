@@ -1,39 +1,427 @@
-use crate::parser::Lines;
+use crate::chords::{Chord, ChordStyle};
+use crate::directives::{CommentStyle, Directive};
+use crate::lexer::Token;
+use crate::parser::{Line, Lines, Phrase};
+use crate::song::{Metadata, Section, SectionKind, Song};
+use crate::text::display_width;
+use logos::Logos;
+
+/// Indentation of the lines of a chorus
+const CHORUS_INDENT: &str = "  ";
+
+/// How to print a chorus repeated with `{chorus}`
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub enum ChorusRecall {
+    /// Only the heading of the chorus, as a reminder to sing it again
+    #[default]
+    Reference,
+    /// The whole chorus, lyrics and chords included
+    Full,
+}
+
+/// How to print lines with chords but no lyrics, e.g. an intro or an instrumental
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub enum ChordRows {
+    /// The chords separated by single spaces, e.g. `C G Am F`
+    #[default]
+    Compact,
+    /// One bar per chord with a dot for every other beat, e.g. `| C . . . | G . . . |`
+    ///
+    /// The beats per bar are taken from the song's `{time}` when it has one
+    Bars { beats: u8 },
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Options {
+    pub chorus_recall: ChorusRecall,
+    pub chord_rows: ChordRows,
+    /// Pads lyrics that are narrower than their chord, so chord names never touch
+    pub stretch_lyrics: bool,
+    /// How the qualities and accidentals of chords are written, e.g. `F#Maj7` or `F♯Δ7`
+    pub chord_style: ChordStyle,
+}
+
+impl Options {
+    /// The options for a particular song, with bars as long as its `{time}`
+    pub(crate) fn for_song(&self, metadata: &Metadata) -> Self {
+        let mut options = self.clone();
+        if let ChordRows::Bars { beats } = &mut options.chord_rows {
+            if let Some(time) = metadata.time.as_deref().and_then(beats_per_bar) {
+                *beats = time;
+            }
+        }
+        options
+    }
+}
 
 fn calc_chord_padding(lyric: &str, chord: &str) -> usize {
-    let mut padding = lyric.len();
-    padding = padding.checked_sub(chord.len()).unwrap_or(0);
-    padding
+    display_width(lyric).saturating_sub(display_width(chord))
+}
+
+/// Lyrics followed by enough filler to leave a space after `chord`
+///
+/// A word split between two chords is continued with hyphens (`a----round`),
+/// anything else is padded with spaces
+fn stretch_lyrics(lyrics: &str, chord: &str, next_lyrics: &str) -> String {
+    let filler = (display_width(chord) + 1).saturating_sub(display_width(lyrics));
+    let mid_word =
+        lyrics.ends_with(char::is_alphanumeric) && next_lyrics.starts_with(char::is_alphanumeric);
+    let filler = if mid_word { "-" } else { " " }.repeat(filler);
+    format!("{lyrics}{filler}")
+}
+
+/// Beats per bar of a time signature such as `3/4` or `6/8`
+fn beats_per_bar(time: &str) -> Option<u8> {
+    let (beats, _) = time.split_once('/')?;
+    beats.trim().parse().ok().filter(|beats| *beats > 0)
+}
+
+/// The chords of a line without lyrics, which is empty for a blank line,
+/// or `None` if the line has lyrics
+fn chords_only(phrases: &[Phrase], style: &ChordStyle) -> Option<Vec<String>> {
+    if !phrases.iter().all(|p| p.lyrics.trim().is_empty()) {
+        return None;
+    }
+    let chords = phrases
+        .iter()
+        .filter_map(|p| p.chord.as_ref().map(|c| c.fmt_style(style)))
+        .collect();
+    Some(chords)
+}
+
+/// Formats the chords of a line without lyrics on a single row
+pub(crate) fn fmt_chord_row(chords: &[String], chord_rows: ChordRows) -> String {
+    match chord_rows {
+        ChordRows::Compact => chords.join(" "),
+        ChordRows::Bars { .. } if chords.is_empty() => String::new(),
+        ChordRows::Bars { beats } => {
+            let rest = " .".repeat(usize::from(beats.saturating_sub(1)));
+            let bars: String = chords.iter().map(|c| format!("| {c}{rest} ")).collect();
+            format!("{bars}|")
+        }
+    }
+}
+
+/// Lines of credits and musical details of a song, e.g. `Composer: Wallace Willis` or
+/// `Key: D | Time: 4/4 | Capo: 2`
+pub fn fmt_credits(metadata: &Metadata) -> Vec<String> {
+    let mut lines = Vec::new();
+    let credits = [
+        ("Artist", &metadata.artists),
+        ("Composer", &metadata.composers),
+        ("Lyricist", &metadata.lyricists),
+        ("Arranger", &metadata.arrangers),
+    ];
+    for (label, names) in credits {
+        if !names.is_empty() {
+            lines.push(format!("{label}: {}", names.join(", ")));
+        }
+    }
+    let details = [
+        ("Album", metadata.album.clone()),
+        ("Year", metadata.year.clone()),
+        ("Copyright", metadata.copyright.clone()),
+    ];
+    for (label, value) in details {
+        if let Some(value) = value {
+            lines.push(format!("{label}: {value}"));
+        }
+    }
+    let music: Vec<String> = [
+        ("Key", metadata.key.map(|key| key.to_string())),
+        ("Tempo", metadata.tempo.clone()),
+        ("Time", metadata.time.clone()),
+        ("Capo", metadata.capo.map(|capo| capo.to_string())),
+    ]
+    .into_iter()
+    .filter_map(|(label, value)| value.map(|value| format!("{label}: {value}")))
+    .collect();
+    if !music.is_empty() {
+        lines.push(music.join(" | "));
+    }
+    lines
+}
+
+/// Formats the title, credits and musical details of a song, followed by a blank line
+pub fn fmt_header(metadata: &Metadata) -> String {
+    if metadata.is_empty() {
+        return String::new();
+    }
+
+    let mut header = String::new();
+    let lines = metadata
+        .title
+        .iter()
+        .chain(&metadata.subtitles)
+        .cloned()
+        .chain(fmt_credits(metadata));
+    for line in lines {
+        header.push_str(&line);
+        header.push('\n');
+    }
+    header.push('\n');
+    header
+}
+
+/// Formats a song with its metadata as a header above the lyrics and chords
+///
+/// Blank lines at the start of the song are dropped, so the header is followed by a single one
+pub fn fmt_song(song: Song, options: &Options) -> String {
+    let mut fmt_song = fmt_header(&song.metadata);
+    let options = options.for_song(&song.metadata);
+    let body: String = song
+        .sections
+        .into_iter()
+        .map(|section| fmt_section(section, &options))
+        .collect();
+    fmt_song.push_str(body.trim_start_matches('\n'));
+    fmt_song
 }
 
-pub fn fmt_lyrics_and_chords(lines: Lines) -> String {
+/// Formats a section under its heading
+///
+/// Choruses are indented, and tabs and grids are printed as written, without a chord line.
+/// A repeated chorus is only printed in full with [`ChorusRecall::Full`]
+pub fn fmt_section(section: Section, options: &Options) -> String {
+    let mut fmt_section = String::new();
+    if section.kind != SectionKind::Body {
+        fmt_section.push_str(&section.heading());
+        fmt_section.push('\n');
+    }
+    if section.recall && options.chorus_recall == ChorusRecall::Reference {
+        return fmt_section;
+    }
+    let body = match section.kind {
+        SectionKind::Tab | SectionKind::Grid => fmt_verbatim(&section.lines),
+        _ => fmt_lyrics_and_chords(section.lines, options),
+    };
+    if section.kind == SectionKind::Chorus {
+        for line in body.lines() {
+            if !line.is_empty() {
+                fmt_section.push_str(CHORUS_INDENT);
+            }
+            fmt_section.push_str(line);
+            fmt_section.push('\n');
+        }
+    } else {
+        fmt_section.push_str(&body);
+    }
+    fmt_section
+}
+
+/// Writes `lines` back out as ChordPro, with chords in brackets before their lyrics
+///
+/// Directives are written in their canonical form, e.g. `{soc}` as `{start_of_chorus}`,
+/// and chords in the given style
+pub fn fmt_chordpro(lines: &Lines, style: &ChordStyle) -> String {
+    let fmt_lines: Vec<String> = lines
+        .lines
+        .values()
+        .map(|line| match line {
+            Line::Lyrics(phrases) => phrases
+                .iter()
+                .map(|p| match &p.chord {
+                    Some(chord) => format!("[{}]{}", chord.fmt_style(style), p.lyrics),
+                    None => p.lyrics.to_string(),
+                })
+                .collect(),
+            Line::Directive(directive) => directive.to_string(),
+            Line::Comment(comment) => format!("#{comment}"),
+        })
+        .collect();
+    fmt_lines.join("\n")
+}
+
+/// Rewrites the chords of the ChordPro `source` in the given style, and nothing else
+///
+/// Unlike [`fmt_chordpro`], line endings, directives, `#` comments and chords
+/// that cannot be parsed are left as written
+pub fn fmt_chords_in_place(source: &str, style: &ChordStyle) -> String {
+    let mut formatted = String::with_capacity(source.len());
+    let mut copied = 0;
+    let mut in_brackets = false;
+    let mut lex = Token::lexer(source);
+    while let Some(token) = lex.next() {
+        if in_brackets && token == Ok(Token::Chord) {
+            // Trailing spaces, as in `[C ]`, are kept along with the brackets
+            let written = lex.slice().trim_end();
+            if let Ok(chord) = Chord::parse(written) {
+                let start = lex.span().start;
+                formatted.push_str(&source[copied..start]);
+                formatted.push_str(&chord.fmt_style(style));
+                copied = start + written.len();
+            }
+        }
+        in_brackets = token == Ok(Token::LSqBracket);
+    }
+    formatted.push_str(&source[copied..]);
+    formatted
+}
+
+/// A line of a section, as every backend prints it
+pub(crate) enum SectionLine<'a> {
+    /// A line without lyrics or chords
+    Blank,
+    /// The chords of a line without lyrics, in the chord style of the song
+    Chords(Vec<String>),
+    /// Lyrics with their chords
+    Lyrics(&'a [Phrase]),
+    /// A `{comment}`, with the class of its style (see [`comment_class`])
+    Comment(Option<&'static str>, &'a str),
+}
+
+/// The lines of a section that are printed, leaving out its other directives and `#` comments
+pub(crate) fn section_lines<'a>(
+    lines: &'a Lines,
+    chord_style: &'a ChordStyle,
+) -> impl Iterator<Item = SectionLine<'a>> {
+    lines.lines.values().filter_map(|line| match line {
+        Line::Lyrics(phrases) => Some(match chords_only(phrases, chord_style) {
+            Some(chords) if chords.is_empty() => SectionLine::Blank,
+            Some(chords) => SectionLine::Chords(chords),
+            None => SectionLine::Lyrics(phrases),
+        }),
+        Line::Directive(Directive::Comment { style, text }) => {
+            Some(SectionLine::Comment(comment_class(*style), text))
+        }
+        Line::Directive(_) | Line::Comment(_) => None,
+    })
+}
+
+/// Class of the style of a `{comment}`, e.g. `italic` for `{comment_italic}`,
+/// or `None` for a plain `{comment}`
+fn comment_class(style: CommentStyle) -> Option<&'static str> {
+    match style {
+        CommentStyle::Normal => None,
+        CommentStyle::Italic => Some("italic"),
+        CommentStyle::Boxed => Some("boxed"),
+        CommentStyle::Highlight => Some("highlight"),
+    }
+}
+
+/// How a backend marks up lyrics and the chords above them
+pub(crate) trait Markup {
+    /// Lyrics without a chord, shown exactly as written
+    fn lyrics(&self, lyrics: &str) -> String;
+    /// A chord with the word it is played on, which is empty for a chord between two words
+    fn chord(&self, chord: &str, word: &str) -> String;
+}
+
+/// Marks up a line of lyrics with its chords, without spaces at its end
+///
+/// Only the word a chord is played on goes with the chord, so that lines can still break
+/// between the other words
+pub(crate) fn fmt_phrases(phrases: &[Phrase], style: &ChordStyle, markup: &impl Markup) -> String {
+    let mut fmt_phrases = String::new();
+    for phrase in phrases {
+        let Some(chord) = &phrase.chord else {
+            fmt_phrases.push_str(&markup.lyrics(&phrase.lyrics));
+            continue;
+        };
+        // A chord before a space is played between the words
+        let end = phrase
+            .lyrics
+            .find(char::is_whitespace)
+            .unwrap_or(phrase.lyrics.len());
+        let (word, rest) = phrase.lyrics.split_at(end);
+        fmt_phrases.push_str(&markup.chord(&chord.fmt_style(style), word));
+        fmt_phrases.push_str(&markup.lyrics(rest));
+    }
+    fmt_phrases.trim_end().to_string()
+}
+
+/// Formats lines exactly as they were written, e.g. for guitar tabs
+pub(crate) fn fmt_verbatim(lines: &Lines) -> String {
+    let mut fmt_lines = String::new();
+    for line in lines.lines.values() {
+        if let Line::Lyrics(phrases) = line {
+            for p in phrases {
+                fmt_lines.push_str(&p.lyrics);
+            }
+            fmt_lines.push('\n');
+        }
+    }
+    fmt_lines
+}
+
+/// Formats each line of lyrics below a line with its chords
+///
+/// Lines without lyrics are printed as a single row of chords (see [`ChordRows`]),
+/// or as a single blank line when they have no chords either.
+/// With [`Options::stretch_lyrics`], lyrics under a chord are padded until the next chord
+/// no longer runs into it
+pub fn fmt_lyrics_and_chords(lines: Lines, options: &Options) -> String {
     let mut fmt_song = String::new();
-    for (_, phrases) in lines.lines.iter() {
-        // TODO: Handle case with newline/empty line - no need to construct a chord line
+    for line in section_lines(&lines, &options.chord_style) {
+        let phrases = match line {
+            SectionLine::Lyrics(phrases) => phrases,
+            SectionLine::Blank => {
+                fmt_song.push('\n');
+                continue;
+            }
+            SectionLine::Chords(chords) => {
+                fmt_song.push_str(&fmt_chord_row(&chords, options.chord_rows));
+                fmt_song.push('\n');
+                continue;
+            }
+            SectionLine::Comment(_, text) => {
+                fmt_song.push_str(text);
+                fmt_song.push('\n');
+                continue;
+            }
+        };
         let mut chord_line = String::new();
         let mut lyric_line = String::new();
-        for p in phrases.into_iter() {
+        let mut phrases = phrases.iter().peekable();
+        while let Some(p) = phrases.next() {
             let chord = p
                 .chord
                 .as_ref()
-                .map_or("".to_string(), |c| c.chord.to_owned());
+                .map_or("".to_string(), |c| c.fmt_style(&options.chord_style));
+            let lyrics = match phrases.peek() {
+                Some(next)
+                    if options.stretch_lyrics && p.chord.is_some() && next.chord.is_some() =>
+                {
+                    stretch_lyrics(&p.lyrics, &chord, &next.lyrics)
+                }
+                _ => p.lyrics.to_string(),
+            };
             chord_line.push_str(&chord);
-            let padding = &" ".repeat(calc_chord_padding(&p.lyrics, &chord));
+            let padding = &" ".repeat(calc_chord_padding(&lyrics, &chord));
             chord_line.push_str(padding);
-            lyric_line.push_str(&p.lyrics);
+            lyric_line.push_str(&lyrics);
         }
         fmt_song.push_str(&chord_line);
-        fmt_song.push_str("\n");
+        fmt_song.push('\n');
         fmt_song.push_str(&lyric_line);
-        fmt_song.push_str("\n");
+        fmt_song.push('\n');
     }
     fmt_song
 }
 
+/// Songs and lines shared by the tests of the backends
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use crate::parser::{self, Lines};
+    use crate::song::Song;
+
+    pub(crate) fn lines(song: &str) -> Lines {
+        parser::parse(song.to_string()).unwrap()
+    }
+
+    /// Two short songs, for documents with several songs in them
+    pub(crate) fn two_songs() -> Vec<Song> {
+        ["{title: One}\n[C]Hi", "{title: Two}\n[G]Bye"]
+            .map(|song| Song::parse(song.to_string()).unwrap())
+            .to_vec()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::parser::Chord;
+    use crate::chords::Chord;
     use crate::parser::Phrase;
+    use crate::pitch::Notation;
 
     use super::*;
 
@@ -57,6 +445,23 @@ mod tests {
         assert_eq!(0, calc_chord_padding("", "C#maj7"));
     }
 
+    #[test]
+    fn test_calc_chord_padding_with_multibyte_lyrics() {
+        assert_eq!(2, calc_chord_padding("Señor", "Am7"));
+        assert_eq!(7, calc_chord_padding("반복되는 ", "Em"));
+    }
+
+    #[test]
+    fn test_fmt_multibyte_lyrics_and_chords() {
+        let lines =
+            crate::parser::parse("[Em]반복되는 [Dm]하루[G]에\nJalape[C]ño [G]time".to_string())
+                .unwrap();
+        assert_eq!(
+            fmt_lyrics_and_chords(lines, &Options::default()),
+            "Em       Dm  G \n반복되는 하루에\n      C  G   \nJalapeño time\n"
+        )
+    }
+
     #[test]
     fn test_fmt_lyrics_and_chords() {
         let mut lines = Lines::new();
@@ -66,7 +471,7 @@ mod tests {
                 "Hi there".to_string(),
                 0,
                 8,
-                Some(Chord::new("C#maj7".to_string())),
+                Some(Chord::new("C#maj7").unwrap()),
             ),
         );
         lines.add_phrase(
@@ -75,13 +480,233 @@ mod tests {
                 "Bye there".to_string(),
                 0,
                 8,
-                Some(Chord::new("Dsus2".to_string())),
+                Some(Chord::new("Dsus2").unwrap()),
             ),
         );
 
+        let options = Options {
+            chord_style: ChordStyle::SPELLED,
+            ..Options::default()
+        };
         assert_eq!(
-            fmt_lyrics_and_chords(lines),
+            fmt_lyrics_and_chords(lines, &options),
             "C#maj7  \nHi there\nDsus2    \nBye there\n".to_string()
         )
     }
+
+    #[test]
+    fn test_stretch_lyrics() {
+        assert_eq!(stretch_lyrics("a", "Ebm9", "round"), "a----");
+        assert_eq!(stretch_lyrics("run ", "Ebm9", "around"), "run  ");
+        assert_eq!(stretch_lyrics("", "C", ""), "  ");
+        assert_eq!(stretch_lyrics("Hi there", "C", "you"), "Hi there");
+    }
+
+    #[test]
+    fn test_fmt_stretched_lyrics() {
+        let options = Options {
+            stretch_lyrics: true,
+            ..Default::default()
+        };
+        let lines =
+            crate::parser::parse("[Ebm9]run a[Ab]round\n[Ebm9]a[Ab]round".to_string()).unwrap();
+        assert_eq!(
+            fmt_lyrics_and_chords(lines.clone(), &Default::default()),
+            "Ebm9 Ab   \nrun around\nEbm9Ab   \naround\n"
+        );
+        assert_eq!(
+            fmt_lyrics_and_chords(lines, &options),
+            "Ebm9 Ab   \nrun around\nEbm9 Ab   \na----round\n"
+        );
+        let lines = crate::parser::parse("[C][G]Hey [Am]you".to_string()).unwrap();
+        assert_eq!(
+            fmt_lyrics_and_chords(lines, &options),
+            "C G   Am \n  Hey you\n"
+        );
+    }
+
+    #[test]
+    fn test_fmt_chord_rows_and_blank_lines() {
+        let lines =
+            crate::parser::parse("[C] [G] [Am] [F]\n\nHello [D]there\n  \n[Em]".to_string())
+                .unwrap();
+        assert_eq!(
+            fmt_lyrics_and_chords(lines.clone(), &Options::default()),
+            "C G Am F\n\n      D    \nHello there\n\nEm\n"
+        );
+        let options = Options {
+            chord_rows: ChordRows::Bars { beats: 4 },
+            ..Default::default()
+        };
+        assert_eq!(
+            fmt_lyrics_and_chords(lines, &options),
+            "| C . . . | G . . . | Am . . . | F . . . |\n\n      D    \nHello there\n\n| Em . . . |\n"
+        );
+    }
+
+    #[test]
+    fn test_fmt_typographic_notation() {
+        let options = Options {
+            chord_style: ChordStyle {
+                notation: Notation::Typographic,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let lines = crate::parser::parse("[F#m7b5]Hello [Bb]you\n[C#] [Gbb]".to_string()).unwrap();
+        assert_eq!(
+            fmt_lyrics_and_chords(lines, &options),
+            "F♯m7♭5B♭ \nHello you\nC♯ G𝄫\n"
+        );
+    }
+
+    #[test]
+    fn test_fmt_bars_in_time_signature() {
+        assert_eq!(beats_per_bar("3/4"), Some(3));
+        assert_eq!(beats_per_bar("6/8"), Some(6));
+        assert_eq!(beats_per_bar("0/4"), None);
+        assert_eq!(beats_per_bar("common"), None);
+
+        let song = Song::parse("{time: 3/4}\n[G] [D7]".to_string()).unwrap();
+        let options = Options {
+            chord_rows: ChordRows::Bars { beats: 4 },
+            ..Default::default()
+        };
+        assert!(fmt_song(song, &options).ends_with("\n| G . . | D7 . . |\n"));
+    }
+
+    #[test]
+    fn test_fmt_directives_and_comments() {
+        let lines = crate::parser::parse(
+            "# not printed\n{title: Hi}\n[G]Hi there\n{comment: Repeat}".to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            fmt_lyrics_and_chords(lines, &Options::default()),
+            "G       \nHi there\nRepeat\n".to_string()
+        )
+    }
+
+    #[test]
+    fn test_fmt_chordpro() {
+        let song = "# Verse\n{start_of_verse: Verse 1}\nI [D]looked over [G/B]Jordan\n\n[C] [G]\n{end_of_verse}\n";
+        let lines = crate::parser::parse(song.to_string()).unwrap();
+        assert_eq!(fmt_chordpro(&lines, &ChordStyle::STANDARD), song);
+
+        let lines = crate::parser::parse("{soc}\n[Cmin]Hi\n{eoc}".to_string()).unwrap();
+        assert_eq!(
+            fmt_chordpro(&lines, &ChordStyle::STANDARD),
+            "{start_of_chorus}\n[Cm]Hi\n{end_of_chorus}"
+        );
+
+        let lines = crate::parser::parse("[Cmaj7]Hi [F#m7]there [Bbdim]you".to_string()).unwrap();
+        assert_eq!(
+            fmt_chordpro(&lines, &ChordStyle::JAZZ),
+            "[CΔ7]Hi [F#-7]there [Bb°]you"
+        );
+    }
+
+    #[test]
+    fn test_fmt_chords_in_place() {
+        let song =
+            "# Verse in [Cmaj7]\r\n{soc}\r\n[Cmaj7 ]Hi [F#m7]there [H7]you\r\n{eoc}\r\n# End";
+        assert_eq!(
+            fmt_chords_in_place(song, &ChordStyle::JAZZ),
+            "# Verse in [Cmaj7]\r\n{soc}\r\n[CΔ7 ]Hi [F#-7]there [H7]you\r\n{eoc}\r\n# End"
+        );
+    }
+
+    #[test]
+    fn test_fmt_song_header() {
+        let song = Song::parse(
+            r#"{title: Swing Low Sweet Chariot}
+{subtitle: Traditional}
+{composer: Wallace Willis}
+{lyricist: Wallace Willis}
+{key: D}
+{capo: 2}
+{time: 4/4}
+[D]Swing low"#
+                .to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            fmt_song(song, &Options::default()),
+            r#"Swing Low Sweet Chariot
+Traditional
+Composer: Wallace Willis
+Lyricist: Wallace Willis
+Key: D | Time: 4/4 | Capo: 2
+
+D        
+Swing low
+"#
+        )
+    }
+
+    #[test]
+    fn test_fmt_song_without_header() {
+        let song = Song::parse("[D]Swing low".to_string()).unwrap();
+        assert_eq!(
+            fmt_song(song, &Options::default()),
+            "D        \nSwing low\n"
+        );
+    }
+
+    #[test]
+    fn test_fmt_song_blank_lines() {
+        let song = Song::parse(
+            "# A comment\n\n{title: Swing Low}\n\n{soc}\nSwing [D]low\n\nSweet [G]chariot\n{eoc}"
+                .to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            fmt_song(song, &Options::default()),
+            "Swing Low\n\nChorus\n        D  \n  Swing low\n\n        G      \n  Sweet chariot\n"
+        );
+    }
+
+    #[test]
+    fn test_fmt_sections() {
+        let song = Song::parse(
+            r#"{start_of_verse: Verse 1}
+I [D]looked over
+{end_of_verse}
+{start_of_chorus}
+Swing [D]low
+{end_of_chorus}
+{start_of_tab}
+e|--0--2--|
+{end_of_tab}"#
+                .to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            fmt_song(song, &Options::default()),
+            r#"Verse 1
+  D          
+I looked over
+Chorus
+        D  
+  Swing low
+Tab
+e|--0--2--|
+"#
+        )
+    }
+
+    #[test]
+    fn test_fmt_chorus_recall() {
+        let song = Song::parse("{soc}\nSwing [D]low\n{eoc}\n{chorus}".to_string()).unwrap();
+        let chorus = "Chorus\n        D  \n  Swing low\n";
+        assert_eq!(
+            fmt_song(song.clone(), &Options::default()),
+            format!("{chorus}Chorus\n")
+        );
+        let options = Options {
+            chorus_recall: ChorusRecall::Full,
+            ..Default::default()
+        };
+        assert_eq!(fmt_song(song, &options), format!("{chorus}{chorus}"));
+    }
 }
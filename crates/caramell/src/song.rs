@@ -0,0 +1,553 @@
+//! Songs and their metadata
+
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::directives::Directive;
+use crate::parser::{self, Line, Lines};
+use crate::pitch::Key;
+use crate::transpose::{self, Transposition};
+use std::collections::BTreeMap;
+
+/// Metadata from directives such as `{title: ...}`, `{artist: ...}` or `{key: G}`
+///
+/// Credits may be given more than once, e.g. a song with two composers
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub subtitles: Vec<String>,
+    pub artists: Vec<String>,
+    pub composers: Vec<String>,
+    pub lyricists: Vec<String>,
+    pub arrangers: Vec<String>,
+    pub album: Option<String>,
+    pub year: Option<String>,
+    pub copyright: Option<String>,
+    pub key: Option<Key>,
+    pub tempo: Option<String>,
+    pub time: Option<String>,
+    pub capo: Option<u8>,
+    /// Any other metadata, e.g. from `{meta: performer Rick Astley}`
+    pub other: BTreeMap<String, String>,
+}
+
+impl Metadata {
+    /// Records a metadata directive, e.g. `{title: ...}`
+    pub fn add(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let value = value.to_string();
+        match name {
+            "title" => self.title = Some(value),
+            "subtitle" => self.subtitles.push(value),
+            "artist" => self.artists.push(value),
+            "composer" => self.composers.push(value),
+            "lyricist" => self.lyricists.push(value),
+            "arranger" => self.arrangers.push(value),
+            "album" => self.album = Some(value),
+            "year" => self.year = Some(value),
+            "copyright" => self.copyright = Some(value),
+            "key" => self.key = Some(Key::new(&value)?),
+            "tempo" => self.tempo = Some(value),
+            "time" => self.time = Some(value),
+            "capo" => {
+                let capo = value
+                    .parse()
+                    .map_err(|_| format!("capo must be a fret number, not '{value}'"))?;
+                self.capo = Some(capo);
+            }
+            _ => {
+                self.other.insert(name.to_string(), value);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Kinds of sections, from environment directives such as `{start_of_chorus}`
+#[derive(Debug, PartialEq, Clone)]
+pub enum SectionKind {
+    /// Lines outside of any environment
+    Body,
+    Verse,
+    Chorus,
+    Bridge,
+    Tab,
+    Grid,
+    /// Any other environment, e.g. `intro` from `{start_of_intro}`
+    Other(String),
+}
+
+impl SectionKind {
+    pub fn new(environment: &str) -> Self {
+        match environment {
+            "verse" => Self::Verse,
+            "chorus" => Self::Chorus,
+            "bridge" => Self::Bridge,
+            "tab" => Self::Tab,
+            "grid" => Self::Grid,
+            _ => Self::Other(environment.to_string()),
+        }
+    }
+
+    /// Name of the section when it has no label, e.g. `Chorus`
+    pub fn name(&self) -> String {
+        match self {
+            Self::Body => String::new(),
+            Self::Verse => "Verse".to_string(),
+            Self::Chorus => "Chorus".to_string(),
+            Self::Bridge => "Bridge".to_string(),
+            Self::Tab => "Tab".to_string(),
+            Self::Grid => "Grid".to_string(),
+            Self::Other(environment) => {
+                let mut chars = environment.chars();
+                chars.next().map_or(String::new(), |first| {
+                    first
+                        .to_uppercase()
+                        .chain(chars)
+                        .collect::<String>()
+                        .replace('_', " ")
+                })
+            }
+        }
+    }
+}
+
+/// A section of a song, e.g. a verse or a chorus
+#[derive(Debug, PartialEq, Clone)]
+pub struct Section {
+    pub kind: SectionKind,
+    /// Label of the section, e.g. `Verse 1` from `{start_of_verse: Verse 1}`
+    pub label: Option<String>,
+    /// Lines of the section, without its start and end directives
+    pub lines: Lines,
+    /// Whether the section repeats an earlier chorus, from a `{chorus}` directive
+    pub recall: bool,
+}
+
+impl Section {
+    pub fn new(kind: SectionKind, label: Option<String>) -> Self {
+        Self {
+            kind,
+            label,
+            lines: Lines::new(),
+            recall: false,
+        }
+    }
+
+    /// Label of the section, falling back to the name of its kind
+    pub fn heading(&self) -> String {
+        self.label.clone().unwrap_or_else(|| self.kind.name())
+    }
+}
+
+/// Splits `lines` into sections at environment directives
+/// Lines outside of any environment are grouped into [`SectionKind::Body`] sections
+///
+/// A misplaced directive is reported in `diagnostics`: a section started inside another one
+/// ends the first, and an unmatched end or a `{chorus}` inside a section is ignored
+fn sections(lines: &Lines, diagnostics: &mut Vec<Diagnostic>) -> Vec<Section> {
+    let mut sections = Vec::new();
+    let mut current = Section::new(SectionKind::Body, None);
+    for (number, line) in lines.lines.iter() {
+        match line {
+            Line::Directive(Directive::StartOf { environment, label }) => {
+                if current.kind != SectionKind::Body {
+                    let message = format!(
+                        "Section error: cannot start {environment} inside {}",
+                        current.kind.name().to_lowercase(),
+                    );
+                    diagnostics.push(line_error(lines, *number, message));
+                }
+                let section = Section::new(SectionKind::new(environment), label.clone());
+                let body = std::mem::replace(&mut current, section);
+                if !body.lines.lines.is_empty() {
+                    sections.push(body);
+                }
+            }
+            Line::Directive(Directive::EndOf { environment }) => {
+                if current.kind != SectionKind::new(environment) {
+                    let message = format!("Section error: end of {environment} without a start");
+                    diagnostics.push(line_error(lines, *number, message));
+                    continue;
+                }
+                sections.push(std::mem::replace(
+                    &mut current,
+                    Section::new(SectionKind::Body, None),
+                ));
+            }
+            Line::Directive(Directive::Chorus { label }) => {
+                if current.kind != SectionKind::Body {
+                    let message = format!(
+                        "Section error: cannot repeat the chorus inside {}",
+                        current.kind.name().to_lowercase(),
+                    );
+                    diagnostics.push(line_error(lines, *number, message));
+                    continue;
+                }
+                let body = std::mem::replace(&mut current, Section::new(SectionKind::Body, None));
+                if !body.lines.lines.is_empty() {
+                    sections.push(body);
+                }
+                sections.push(recall_chorus(&sections, label.clone()));
+            }
+            _ => current.lines.add_line(*number, line.clone()),
+        }
+    }
+    // An environment that is never ended runs to the end of the song
+    if current.kind != SectionKind::Body || !current.lines.lines.is_empty() {
+        sections.push(current);
+    }
+    sections
+}
+
+/// An error about a whole line of `lines`, e.g. a misplaced directive
+fn line_error(lines: &Lines, line: usize, message: String) -> Diagnostic {
+    Diagnostic {
+        span: lines.span(line),
+        line: line + 1,
+        column: 1,
+        severity: Severity::Error,
+        message,
+    }
+}
+
+/// Repeats the most recent chorus before `{chorus}`, or an empty chorus if there is none yet
+/// The label of the directive takes precedence over the label of the repeated chorus
+fn recall_chorus(sections: &[Section], label: Option<String>) -> Section {
+    let chorus = sections
+        .iter()
+        .rev()
+        .find(|section| section.kind == SectionKind::Chorus && !section.recall);
+    let mut recall = match chorus {
+        Some(chorus) => chorus.clone(),
+        None => Section::new(SectionKind::Chorus, None),
+    };
+    recall.label = label.or(recall.label);
+    recall.recall = true;
+    recall
+}
+
+/// A song, made of its metadata and its lines
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Song {
+    pub metadata: Metadata,
+    /// Every line of the song, including directives
+    pub lines: Lines,
+    /// The lines of the song, grouped into verses, choruses and other sections
+    pub sections: Vec<Section>,
+}
+
+impl Song {
+    /// Collects the metadata directives of `lines` and splits them into sections
+    /// The directives stay in `lines`, so the song can still be written back out as it was
+    pub fn new(lines: Lines) -> Result<Self, Diagnostic> {
+        let (song, diagnostics) = Self::new_recovering(lines);
+        match diagnostics.into_iter().next() {
+            Some(diagnostic) => Err(diagnostic),
+            None => Ok(song),
+        }
+    }
+
+    /// Like [`Song::new`], but skips invalid metadata and misplaced section directives,
+    /// and returns every problem found along with the song
+    pub fn new_recovering(lines: Lines) -> (Self, Vec<Diagnostic>) {
+        let mut metadata = Metadata::default();
+        let mut diagnostics = Vec::new();
+        for (number, line) in lines.lines.iter() {
+            if let Line::Directive(Directive::Metadata { name, value }) = line {
+                if let Err(err) = metadata.add(name, value) {
+                    let message = format!("Metadata error: {err}");
+                    diagnostics.push(line_error(&lines, *number, message));
+                }
+            }
+        }
+        let sections = sections(&lines, &mut diagnostics);
+        let song = Self {
+            metadata,
+            lines,
+            sections,
+        };
+        (song, diagnostics)
+    }
+
+    pub fn parse(song: String) -> Result<Self, Diagnostic> {
+        Self::new(parser::parse(song)?)
+    }
+
+    /// Parses as much of a song as possible, returning it with every problem found in it,
+    /// in the order they appear in the song
+    pub fn parse_recovering(song: String) -> (Self, Vec<Diagnostic>) {
+        let (lines, mut diagnostics) = parser::parse_recovering(song);
+        let (song, song_diagnostics) = Self::new_recovering(lines);
+        diagnostics.extend(song_diagnostics);
+        diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+        (song, diagnostics)
+    }
+
+    /// Transposes every chord of the song, along with its `{key}`
+    ///
+    /// The source key is the first `{key}` of the song if there is one, or else the key of its first chord.
+    /// Fails if the target key is in another mode than the song
+    pub fn transpose(&self, transposition: &Transposition) -> Result<Self, String> {
+        let lines = transpose::transpose(&self.lines, transposition)?;
+        // Transposing does not change the structure of the song, so any problems were already there
+        Ok(Self::new_recovering(lines).0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pitch::{Mode, Note};
+
+    #[test]
+    fn test_song_metadata() {
+        let song = Song::parse(
+            r#"{title: Swing Low Sweet Chariot}
+{subtitle: Traditional}
+{artist: Fisk Jubilee Singers}
+{composer: Wallace Willis}
+{composer: Someone Else}
+{lyricist: Wallace Willis}
+{arranger: Me}
+{key: D}
+{tempo: 80}
+{time: 4/4}
+{capo: 2}
+{year: 1909}
+{album: Spirituals}
+{copyright: Public domain}
+{meta: performer Etta James}
+Swing [D]low"#
+                .to_string(),
+        )
+        .unwrap();
+
+        let metadata = song.metadata;
+        assert_eq!(metadata.title, Some("Swing Low Sweet Chariot".to_string()));
+        assert_eq!(metadata.subtitles, vec!["Traditional"]);
+        assert_eq!(metadata.artists, vec!["Fisk Jubilee Singers"]);
+        assert_eq!(metadata.composers, vec!["Wallace Willis", "Someone Else"]);
+        assert_eq!(metadata.lyricists, vec!["Wallace Willis"]);
+        assert_eq!(metadata.arrangers, vec!["Me"]);
+        assert_eq!(metadata.key, Some(Key::new("D").unwrap()));
+        assert_eq!(metadata.tempo, Some("80".to_string()));
+        assert_eq!(metadata.time, Some("4/4".to_string()));
+        assert_eq!(metadata.capo, Some(2));
+        assert_eq!(metadata.year, Some("1909".to_string()));
+        assert_eq!(metadata.album, Some("Spirituals".to_string()));
+        assert_eq!(metadata.copyright, Some("Public domain".to_string()));
+        assert_eq!(metadata.other["performer"], "Etta James");
+    }
+
+    #[test]
+    fn test_song_without_metadata() {
+        let song = Song::parse("[C]Hello".to_string()).unwrap();
+        assert!(song.metadata.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_metadata() {
+        let err = Song::parse("[C]Hello\n{capo: two}".to_string()).unwrap_err();
+        assert_eq!(
+            err.message,
+            "Metadata error: capo must be a fret number, not 'two'"
+        );
+        assert_eq!((err.span, err.line), (9..20, 2));
+        assert!(Song::parse("{key: H}".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_transpose_song_key() {
+        let song = Song::parse("{key: Am}\n[C]Hello [Am]there".to_string()).unwrap();
+        let song = song.transpose(&"2".parse().unwrap()).unwrap();
+        assert_eq!(
+            song.metadata.key,
+            Some(Key {
+                tonic: Note::new("B").unwrap(),
+                mode: Mode::Minor
+            })
+        );
+        assert_eq!(
+            song.lines.lines[&0],
+            Line::Directive(Directive::new("{key: Bm}").unwrap())
+        );
+        let chords: Vec<String> = song
+            .lines
+            .phrases()
+            .filter_map(|p| p.chord.as_ref().map(|c| c.to_string()))
+            .collect();
+        assert_eq!(chords, vec!["D", "Bm"]);
+    }
+
+    #[test]
+    fn test_transpose_song_with_key_change() {
+        let song = Song::parse("{key: C}\n[C]One [G]two\n{key: D}\n[D]Three [F#m]four".to_string())
+            .unwrap();
+        let keys = |song: &Song| {
+            [0, 2].map(|line| match &song.lines.lines[&line] {
+                Line::Directive(Directive::Metadata { value, .. }) => value.clone(),
+                line => panic!("expected a key, got {line:?}"),
+            })
+        };
+        let chords = |song: &Song| -> Vec<String> {
+            song.lines
+                .phrases()
+                .filter_map(|p| p.chord.as_ref().map(|c| c.to_string()))
+                .collect()
+        };
+
+        let up = song.transpose(&"2".parse().unwrap()).unwrap();
+        assert_eq!(keys(&up), ["D", "E"]);
+        assert_eq!(chords(&up), vec!["D", "A", "E", "G#m"]);
+        assert_eq!(up.metadata.key.unwrap().to_string(), "E");
+
+        let to_f = song.transpose(&"F".parse().unwrap()).unwrap();
+        assert_eq!(keys(&to_f), ["F", "G"]);
+        assert_eq!(chords(&to_f), vec!["F", "C", "G", "Bm"]);
+    }
+
+    #[test]
+    fn test_transpose_song_in_mode() {
+        let song = Song::parse("{key: D dorian}\n[Dm7]Hello [G7]there".to_string()).unwrap();
+        assert_eq!(song.metadata.key.unwrap().mode, Mode::Dorian);
+        let song = song.transpose(&"G".parse().unwrap()).unwrap();
+        assert_eq!(song.metadata.key.unwrap().to_string(), "G dorian");
+        assert_eq!(
+            song.lines.lines[&0],
+            Line::Directive(Directive::new("{key: G dorian}").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_song_sections() {
+        let song = Song::parse(
+            r#"{title: Swing Low}
+
+{start_of_verse: Verse 1}
+I [D]looked over Jordan
+{end_of_verse}
+{soc}
+Swing [D]low
+{eoc}
+{start_of_intro}
+[D] [G]
+{end_of_intro}
+{sot}"#
+                .to_string(),
+        )
+        .unwrap();
+
+        let kinds: Vec<(SectionKind, String, usize)> = song
+            .sections
+            .iter()
+            .map(|s| (s.kind.clone(), s.heading(), s.lines.lines.len()))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (SectionKind::Body, "".to_string(), 2),
+                (SectionKind::Verse, "Verse 1".to_string(), 1),
+                (SectionKind::Chorus, "Chorus".to_string(), 1),
+                (
+                    SectionKind::Other("intro".to_string()),
+                    "Intro".to_string(),
+                    1
+                ),
+                (SectionKind::Tab, "Tab".to_string(), 0),
+            ]
+        );
+        assert_eq!(
+            song.sections[1].lines.lines.keys().collect::<Vec<_>>(),
+            vec![&3]
+        );
+    }
+
+    #[test]
+    fn test_mismatched_sections() {
+        assert_eq!(
+            Song::parse("{soc}\n{sov}".to_string()),
+            Err(Diagnostic {
+                span: 6..11,
+                line: 2,
+                column: 1,
+                severity: Severity::Error,
+                message: "Section error: cannot start verse inside chorus".to_string()
+            })
+        );
+        let err = Song::parse("{soc}\n{eov}".to_string()).unwrap_err();
+        assert_eq!(err.message, "Section error: end of verse without a start");
+        assert_eq!(err.line, 2);
+        assert!(Song::parse("{eoc}".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_parse_recovering() {
+        let (song, diagnostics) = Song::parse_recovering(
+            "{capo: two}\n{sov}\n[C]Hello [X]there\n{soc}\nSwing [D]low\n{eov}\n{title: Hi}"
+                .to_string(),
+        );
+        let messages: Vec<(usize, &str)> = diagnostics
+            .iter()
+            .map(|d| (d.line, d.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (1, "Metadata error: capo must be a fret number, not 'two'"),
+                (3, "Chord error: unknown chord \"X\""),
+                (4, "Section error: cannot start chorus inside verse"),
+                (6, "Section error: end of verse without a start"),
+            ]
+        );
+        assert_eq!(song.metadata.title, Some("Hi".to_string()));
+        assert_eq!(song.metadata.capo, None);
+        let kinds: Vec<&SectionKind> = song.sections.iter().map(|s| &s.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                &SectionKind::Body,
+                &SectionKind::Verse,
+                &SectionKind::Chorus
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chorus_recall() {
+        let song = Song::parse(
+            r#"{chorus}
+{soc: Refrain}
+Swing [D]low
+{eoc}
+Verse
+{chorus}
+{soc}
+Swing [G]high
+{eoc}
+{chorus: Last time}"#
+                .to_string(),
+        )
+        .unwrap();
+
+        let recalls: Vec<(bool, String, usize)> = song
+            .sections
+            .iter()
+            .map(|s| (s.recall, s.heading(), s.lines.lines.len()))
+            .collect();
+        assert_eq!(
+            recalls,
+            vec![
+                (true, "Chorus".to_string(), 0),
+                (false, "Refrain".to_string(), 1),
+                (false, "".to_string(), 1),
+                (true, "Refrain".to_string(), 1),
+                (false, "Chorus".to_string(), 1),
+                (true, "Last time".to_string(), 1),
+            ]
+        );
+        assert_eq!(song.sections[5].lines, song.sections[4].lines);
+        assert!(Song::parse("{sov}\n{chorus}".to_string()).is_err());
+    }
+}
@@ -0,0 +1,27 @@
+//! Measuring text as it is laid out in a terminal or a monospace font
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Number of terminal columns taken up by `s`
+///
+/// Each grapheme cluster (e.g. a letter with its accents, or a Hangul syllable) takes up
+/// its East Asian display width, so wide CJK characters take up two columns
+pub fn display_width(s: &str) -> usize {
+    s.graphemes(true).map(|g| g.width()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(display_width("Hi there"), 8);
+        assert_eq!(display_width("Comin’"), 6);
+        // e + combining acute accent
+        assert_eq!(display_width("cafe\u{301}"), 4);
+        assert_eq!(display_width("하루에"), 6);
+        assert_eq!(display_width("日本"), 4);
+    }
+}
@@ -0,0 +1,295 @@
+//! Song-level transposition
+
+use crate::chords::{Chord, ChordQuality};
+use crate::directives::Directive;
+use crate::parser::{Line, Lines};
+use crate::pitch::{Key, Mode, Note};
+use std::ops::Range;
+use std::str::FromStr;
+
+/// Most sharps or flats a target key keeps; a key with 7 has an enharmonic key with 5
+const MAX_KEY_SIGNATURE: i32 = 6;
+
+/// Key of the first chord in the song, if any
+pub fn guess_key(lines: &Lines) -> Option<Key> {
+    let chord = lines.phrases().find_map(|phrase| phrase.chord.as_ref())?;
+    let mode = match chord.quality {
+        ChordQuality::Minor | ChordQuality::MinorMajor => Mode::Minor,
+        _ => Mode::Major,
+    };
+    Some(Key {
+        tonic: chord.root,
+        mode,
+    })
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Transposition {
+    /// Moves every chord by a signed number of half steps
+    HalfSteps(i32),
+    /// Moves every chord so that the song ends up on the given tonic
+    ///
+    /// Without a mode the song keeps its own, so `Bb` moves a song in A minor to Bb minor
+    Key { tonic: Note, mode: Option<Mode> },
+}
+
+impl FromStr for Transposition {
+    type Err = String;
+
+    /// Parses either a number of half steps (`-2`, `+3`) or a target key (`Bb`, `F#m`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<i32>() {
+            Ok(half_steps) => Ok(Self::HalfSteps(half_steps)),
+            Err(_) => match Note::new(s) {
+                Ok(tonic) => Ok(Self::Key { tonic, mode: None }),
+                Err(_) => {
+                    let key = Key::new(s)?;
+                    Ok(Self::Key {
+                        tonic: key.tonic,
+                        mode: Some(key.mode),
+                    })
+                }
+            },
+        }
+    }
+}
+
+/// Transposes every chord in `lines`, including slash bass notes, along with every `{key}`
+///
+/// Chords are spelled consistently for their key, so a chart never mixes e.g. C# and Db.
+/// The source key is the first `{key}` of the song if there is one, or else the key of its first chord
+pub fn transpose(lines: &Lines, transposition: &Transposition) -> Result<Lines, String> {
+    match keys(lines).next().or_else(|| guess_key(lines)) {
+        Some(from) => transpose_from(lines, from, transposition),
+        None => Ok(lines.clone()),
+    }
+}
+
+/// Transposes every chord and `{key}` in `lines` from the key `from`
+///
+/// Transposing only moves the tonic, so a target key in another mode than `from`
+/// (e.g. `Em` for a song in C major) is an error.
+/// A target key with 7 or more sharps or flats is respelled, e.g. G# becomes Ab.
+/// When the song changes key, every later `{key}` moves by the same number of half steps
+/// and the chords after it are spelled in that key
+pub fn transpose_from(
+    lines: &Lines,
+    from: Key,
+    transposition: &Transposition,
+) -> Result<Lines, String> {
+    let (half_steps, mut to) = match transposition {
+        Transposition::HalfSteps(half_steps) => (*half_steps, from.transpose(*half_steps)),
+        Transposition::Key { tonic, mode } => {
+            if let Some(mode) = mode.filter(|mode| *mode != from.mode) {
+                return Err(format!(
+                    "cannot transpose a song in {} {} to {tonic} {mode}",
+                    from.tonic, from.mode
+                ));
+            }
+            let half_steps = tonic.semitones() - from.tonic.semitones();
+            let to = Key {
+                tonic: *tonic,
+                mode: from.mode,
+            };
+            let to = if to.signature().abs() > MAX_KEY_SIGNATURE {
+                to.simplest()
+            } else {
+                to
+            };
+            (half_steps, to)
+        }
+    };
+
+    let mut transposed = lines.clone();
+    let mut first_key = true;
+    for line in transposed.lines.values_mut() {
+        match line {
+            Line::Directive(Directive::Metadata { name, value }) if name == "key" => {
+                let Ok(key) = Key::new(value) else {
+                    continue;
+                };
+                if !first_key {
+                    to = key.transpose(half_steps);
+                }
+                first_key = false;
+                *value = to.to_string();
+            }
+            Line::Lyrics(phrases) => {
+                let move_note = |note: &Note| to.spell(&note.transpose(half_steps));
+                for phrase in phrases {
+                    if let Some(chord) = &phrase.chord {
+                        phrase.chord = Some(Chord {
+                            root: move_note(&chord.root),
+                            bass: chord.bass.as_ref().map(move_note),
+                            ..chord.clone()
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(transposed)
+}
+
+/// Byte range of the line the source key of [`transpose`] is taken from,
+/// i.e. the first valid `{key}`, or else the first line with a chord
+pub fn key_span(lines: &Lines) -> Option<Range<usize>> {
+    let is_key = |line: &Line| match line {
+        Line::Directive(Directive::Metadata { name, value }) if name == "key" => {
+            Key::new(value).is_ok()
+        }
+        _ => false,
+    };
+    let has_chord = |line: &Line| match line {
+        Line::Lyrics(phrases) => phrases.iter().any(|phrase| phrase.chord.is_some()),
+        _ => false,
+    };
+    let find = |matches: &dyn Fn(&Line) -> bool| {
+        lines
+            .lines
+            .iter()
+            .find_map(|(number, line)| matches(line).then_some(*number))
+    };
+    let number = find(&is_key).or_else(|| find(&has_chord))?;
+    Some(lines.span(number))
+}
+
+/// Every valid `{key}` of the song, in order
+fn keys(lines: &Lines) -> impl Iterator<Item = Key> + '_ {
+    lines.lines.values().filter_map(|line| match line {
+        Line::Directive(Directive::Metadata { name, value }) if name == "key" => {
+            Key::new(value).ok()
+        }
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn chords(lines: &Lines) -> Vec<String> {
+        lines
+            .phrases()
+            .filter_map(|phrase| phrase.chord.as_ref().map(|c| c.to_string()))
+            .collect()
+    }
+
+    fn transposed(song: &str, transposition: &str) -> Vec<String> {
+        let lines = parse(song.to_string()).unwrap();
+        chords(&transpose(&lines, &transposition.parse().unwrap()).unwrap())
+    }
+
+    #[test]
+    fn parse_transposition() {
+        assert_eq!("-2".parse(), Ok(Transposition::HalfSteps(-2)));
+        assert_eq!("+3".parse(), Ok(Transposition::HalfSteps(3)));
+        assert_eq!(
+            "F#m".parse(),
+            Ok(Transposition::Key {
+                tonic: Note::new("F#").unwrap(),
+                mode: Some(Mode::Minor)
+            })
+        );
+        assert_eq!(
+            "Bb".parse(),
+            Ok(Transposition::Key {
+                tonic: Note::new("Bb").unwrap(),
+                mode: None
+            })
+        );
+        assert!("m".parse::<Transposition>().is_err());
+        assert!("H".parse::<Transposition>().is_err());
+    }
+
+    #[test]
+    fn transpose_by_half_steps() {
+        assert_eq!(
+            transposed("[C]Hello [G/B]there [Am]my [F]friend", "2"),
+            vec!["D", "A/C#", "Bm", "G"]
+        );
+        assert_eq!(
+            transposed("[Ebm9]give you [Ab]up [Fm7]let you [Bbm]down", "-1"),
+            vec!["Dm9", "G", "Em7", "Am"]
+        );
+    }
+
+    #[test]
+    fn transpose_to_key() {
+        assert_eq!(
+            transposed("[G]Some[D]body once [Am]told [C]me", "Bb"),
+            vec!["Bb", "F", "Cm", "Eb"]
+        );
+        assert_eq!(
+            transposed("[Am]Hey [E7]hey [F]ho [G]ho", "Dm"),
+            vec!["Dm", "A7", "Bb", "C"]
+        );
+    }
+
+    #[test]
+    fn transpose_to_key_with_too_many_accidentals() {
+        assert_eq!(
+            transposed("[C]a [G]b [Am]c [F]d [E]e", "G#"),
+            vec!["Ab", "Eb", "Fm", "Db", "C"]
+        );
+        assert_eq!(transposed("[C]a [G]b", "B#"), vec!["C", "G"]);
+        assert_eq!(transposed("[C]a [G]b [F]c", "Fb"), vec!["E", "B", "A"]);
+        assert_eq!(transposed("[Am]a [E]b [F]c", "A#m"), vec!["Bbm", "F", "Gb"]);
+        assert_eq!(transposed("[Am]a [E]b [F]c", "A#"), vec!["Bbm", "F", "Gb"]);
+    }
+
+    #[test]
+    fn key_span_points_at_the_source_key() {
+        let span = |song: &str| key_span(&parse(song.to_string()).unwrap());
+        assert_eq!(span("Hi\n[C]Hello\n{key: G}\n[G]there"), Some(12..20));
+        assert_eq!(span("Hi\n[C]Hello\n{key: H}"), Some(3..11));
+        assert_eq!(span("Hi"), None);
+    }
+
+    #[test]
+    fn transpose_to_key_in_another_mode() {
+        let lines = parse("[C]Hello [Am]there".to_string()).unwrap();
+        assert_eq!(
+            transpose(&lines, &"Em".parse().unwrap()),
+            Err("cannot transpose a song in C major to E minor".to_string())
+        );
+        assert_eq!(
+            transposed("[C]Hello [Am]there", "E major"),
+            vec!["E", "C#m"]
+        );
+        assert_eq!(transposed("[Am]Hello [C]there", "E"), vec!["Em", "G"]);
+    }
+
+    #[test]
+    fn transpose_keeps_spelling_consistent() {
+        // Db and C# are the same pitch, but only one spelling may appear in a key
+        assert_eq!(
+            transposed("[C]a [C#]b [Db]c [Bb]d", "0"),
+            vec!["C", "Db", "Db", "Bb"]
+        );
+        assert_eq!(
+            transposed("[E]a [C#m]b [Db]c [Bb]d", "0"),
+            vec!["E", "C#m", "C#", "A#"]
+        );
+    }
+
+    #[test]
+    fn transpose_by_nothing_keeps_a_key_with_many_sharps() {
+        assert_eq!(
+            transposed("{key: F#}\n[F#]a [C#]b [E#dim]c [G#m]d", "0"),
+            vec!["F#", "C#", "E#dim", "G#m"]
+        );
+        assert_eq!(
+            transposed("{key: C#}\n[C#]a [B#dim]b [E#m]c", "0"),
+            vec!["C#", "B#dim", "E#m"]
+        );
+    }
+
+    #[test]
+    fn transpose_without_chords() {
+        let lines = parse("Just lyrics".to_string()).unwrap();
+        assert_eq!(transpose(&lines, &Transposition::HalfSteps(3)), Ok(lines));
+    }
+}
@@ -0,0 +1,271 @@
+//! Typst output, for typesetting songs with <https://typst.app>
+//!
+//! Each chord is stacked in a box above the lyrics it is played on, so chords stay over their
+//! syllables in any font, and the lyrics make room for chords wider than themselves
+
+use crate::parser::Lines;
+use crate::printing::{self, ChordRows, ChorusRecall, Markup, Options, SectionLine};
+use crate::song::{Section, SectionKind, Song};
+
+/// Page setup and the functions used by the rest of the document
+const PREAMBLE: &str = r##"#set page(margin: 2cm)
+#set text(size: 11pt)
+
+#let chord-name(name) = text(weight: "bold", fill: rgb("#8b1a1a"), name)
+// A chord stacked above the lyrics it is played on, or above an invisible letter if there are none
+#let chord(name, lyrics) = box(stack(
+  dir: ttb,
+  spacing: 0.35em,
+  pad(right: 0.3em, chord-name(name)),
+  if lyrics == none { box(width: 0pt, hide("X")) } else { lyrics },
+))
+#let chord-row(names) = names.map(chord-name).join(h(1em))
+#let section-label(label) = block(above: 1.4em, below: 0.8em, text(weight: "bold", label))
+#let chorus(body) = pad(left: 1.5em, body)
+#let comment(body, style: none) = block(text(fill: luma(35%), if style == "italic" {
+  emph(body)
+} else if style == "boxed" {
+  box(stroke: 0.5pt, inset: 3pt, body)
+} else if style == "highlight" {
+  highlight(body)
+} else {
+  body
+}))
+"##;
+
+/// Writes `s` as a Typst string literal, which is shown exactly as written
+fn string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Formats a song as a standalone Typst document, with its metadata as a title block
+pub fn fmt_song(song: Song, options: &Options) -> String {
+    fmt_songs(vec![song], options)
+}
+
+/// Formats songs as a single Typst document, e.g. a songbook, with each song on a new page
+pub fn fmt_songs(songs: Vec<Song>, options: &Options) -> String {
+    let songs: Vec<String> = songs
+        .into_iter()
+        .map(|song| {
+            let options = options.for_song(&song.metadata);
+            let mut fmt_song = fmt_title_block(&song);
+            for section in song.sections {
+                fmt_song.push_str(&fmt_section(section, &options));
+            }
+            fmt_song
+        })
+        .collect();
+    format!("{PREAMBLE}\n{}", songs.join("#pagebreak()\n\n"))
+}
+
+fn fmt_title_block(song: &Song) -> String {
+    let metadata = &song.metadata;
+    let mut lines = Vec::new();
+    if let Some(title) = &metadata.title {
+        lines.push(format!(
+            "#text(size: 1.8em, weight: \"bold\", {})",
+            string(title)
+        ));
+    }
+    for subtitle in &metadata.subtitles {
+        lines.push(format!("#text(size: 1.2em, {})", string(subtitle)));
+    }
+    for credit in printing::fmt_credits(metadata) {
+        lines.push(format!("#{}", string(&credit)));
+    }
+    if lines.is_empty() {
+        return String::new();
+    }
+    format!("#align(center)[\n  {}\n]\n\n", lines.join(" \\\n  "))
+}
+
+/// Formats a section under its label, with choruses indented
+pub fn fmt_section(section: Section, options: &Options) -> String {
+    let mut fmt_section = String::new();
+    if section.kind != SectionKind::Body {
+        fmt_section.push_str(&format!("#section-label({})\n", string(&section.heading())));
+    }
+    if section.recall && options.chorus_recall == ChorusRecall::Reference {
+        return fmt_section;
+    }
+    let body = match section.kind {
+        SectionKind::Tab | SectionKind::Grid => fmt_verbatim(&section.lines),
+        _ => fmt_lines(&section.lines, options),
+    };
+    if body.is_empty() {
+        return fmt_section;
+    }
+    if section.kind == SectionKind::Chorus {
+        fmt_section.push_str(&format!("#chorus[\n{body}]\n"));
+    } else {
+        fmt_section.push_str(&body);
+    }
+    fmt_section.push('\n');
+    fmt_section
+}
+
+/// Formats lines exactly as they were written, in a monospace block
+fn fmt_verbatim(lines: &Lines) -> String {
+    let text = printing::fmt_verbatim(lines);
+    let text = text.strip_suffix('\n').unwrap_or(&text);
+    format!("#raw(block: true, {})\n", string(text))
+}
+
+/// Formats lyrics with their chords, one line break per line and a paragraph break per blank line
+fn fmt_lines(lines: &Lines, options: &Options) -> String {
+    let mut fmt_lines = String::new();
+    for line in printing::section_lines(lines, &options.chord_style) {
+        match line {
+            SectionLine::Blank => fmt_lines.push('\n'),
+            SectionLine::Chords(chords) => {
+                let row = match options.chord_rows {
+                    ChordRows::Compact => {
+                        let names: Vec<String> = chords.iter().map(|c| string(c)).collect();
+                        format!("#chord-row(({},))", names.join(", "))
+                    }
+                    ChordRows::Bars { .. } => format!(
+                        "#chord-name({})",
+                        string(&printing::fmt_chord_row(&chords, options.chord_rows))
+                    ),
+                };
+                fmt_lines.push_str(&format!("{row} \\\n"));
+            }
+            SectionLine::Lyrics(phrases) => {
+                let phrases = printing::fmt_phrases(phrases, &options.chord_style, &Typst);
+                fmt_lines.push_str(&format!("{phrases} \\\n"));
+            }
+            SectionLine::Comment(class, text) => {
+                let style =
+                    class.map_or(String::new(), |class| format!(", style: {}", string(class)));
+                fmt_lines.push_str(&format!("#comment({}{style})\n", string(text)));
+            }
+        }
+    }
+    fmt_lines
+}
+
+/// Lyrics as Typst strings, with chords stacked above them in boxes
+struct Typst;
+
+impl Markup for Typst {
+    fn lyrics(&self, lyrics: &str) -> String {
+        let text = lyrics.trim();
+        let leading = if lyrics.starts_with(char::is_whitespace) {
+            " "
+        } else {
+            ""
+        };
+        if text.is_empty() {
+            return leading.to_string();
+        }
+        let trailing = if lyrics.ends_with(char::is_whitespace) {
+            " "
+        } else {
+            ""
+        };
+        format!("{leading}#{}{trailing}", string(text))
+    }
+
+    fn chord(&self, chord: &str, word: &str) -> String {
+        if word.is_empty() {
+            format!("#chord({}, none)", string(chord))
+        } else {
+            format!("#chord({}, {})", string(chord), string(word))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::printing::fixtures::{lines, two_songs};
+
+    #[test]
+    fn test_string() {
+        assert_eq!(string(r#"Say "hi" \ #1"#), r#""Say \"hi\" \\ #1""#);
+    }
+
+    #[test]
+    fn test_fmt_lines() {
+        assert_eq!(
+            fmt_lines(
+                &lines("Never gonna [Ebm9]give you [Ab]up\nswing [D]low, sweet [G]chari[D]ot"),
+                &Options::default()
+            ),
+            r##"#"Never gonna" #chord("Ebm9", "give") #"you" #chord("Ab", "up") \
+#"swing" #chord("D", "low,") #"sweet" #chord("G", "chari")#chord("D", "ot") \
+"##
+        );
+    }
+
+    #[test]
+    fn test_fmt_chords_between_words() {
+        assert_eq!(
+            fmt_lines(
+                &lines("the sh[Cm]ed [Eb]\nshape [Bb] of an"),
+                &Options::default()
+            ),
+            r##"#"the sh"#chord("Cm", "ed") #chord("Eb", none) \
+#"shape" #chord("Bb", none) #"of an" \
+"##
+        );
+    }
+
+    #[test]
+    fn test_fmt_chord_rows_blank_lines_and_comments() {
+        let song = lines("[C] [G]\n\n{ci: Slowly}");
+        assert_eq!(
+            fmt_lines(&song, &Options::default()),
+            "#chord-row((\"C\", \"G\",)) \\\n\n#comment(\"Slowly\", style: \"italic\")\n"
+        );
+        let options = Options {
+            chord_rows: ChordRows::Bars { beats: 2 },
+            ..Default::default()
+        };
+        assert_eq!(
+            fmt_lines(&song, &options),
+            "#chord-name(\"| C . | G . |\") \\\n\n#comment(\"Slowly\", style: \"italic\")\n"
+        );
+    }
+
+    #[test]
+    fn test_fmt_songs() {
+        let document = fmt_songs(two_songs(), &Options::default());
+        assert_eq!(document.matches(PREAMBLE).count(), 1);
+        assert!(document.contains(
+            "#pagebreak()\n\n#align(center)[\n  #text(size: 1.8em, weight: \"bold\", \"Two\")"
+        ));
+    }
+
+    #[test]
+    fn test_fmt_song() {
+        let song = Song::parse(
+            "{title: Swing Low}\n{composer: Wallace Willis}\n{key: D}\n{soc}\nSwing [D]low\n{eoc}\n{sot}\ne|--0--|\n{eot}\n{chorus}"
+                .to_string(),
+        )
+        .unwrap();
+        let document = fmt_song(song, &Options::default());
+        assert!(document.starts_with(PREAMBLE));
+        assert_eq!(
+            &document[PREAMBLE.len()..],
+            r##"
+#align(center)[
+  #text(size: 1.8em, weight: "bold", "Swing Low") \
+  #"Composer: Wallace Willis" \
+  #"Key: D"
+]
+
+#section-label("Chorus")
+#chorus[
+#"Swing" #chord("D", "low") \
+]
+
+#section-label("Tab")
+#raw(block: true, "e|--0--|")
+
+#section-label("Chorus")
+"##
+        );
+    }
+}